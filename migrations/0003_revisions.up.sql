@@ -0,0 +1,13 @@
+-- Every change to a joke is recorded as a revision.
+
+CREATE TABLE IF NOT EXISTS revisions (
+  revision SERIAL PRIMARY KEY,
+  id TEXT NOT NULL,
+  action TEXT NOT NULL,
+  author TEXT NOT NULL,
+  created TIMESTAMPTZ NOT NULL DEFAULT now(),
+  joke JSONB,
+  diff JSONB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS revisions_id ON revisions (id);
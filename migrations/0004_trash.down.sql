@@ -0,0 +1 @@
+ALTER TABLE jokes DROP COLUMN IF EXISTS deleted;
@@ -0,0 +1,3 @@
+-- Deleted jokes stay in the trash until purged.
+
+ALTER TABLE jokes ADD COLUMN IF NOT EXISTS deleted TIMESTAMPTZ;
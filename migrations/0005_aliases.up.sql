@@ -0,0 +1,6 @@
+-- Old ids of renamed jokes, kept so that old links still work.
+
+CREATE TABLE IF NOT EXISTS aliases (
+  alias TEXT PRIMARY KEY,
+  id TEXT NOT NULL REFERENCES jokes(id)
+);
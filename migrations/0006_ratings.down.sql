@@ -0,0 +1,3 @@
+ALTER TABLE jokes DROP COLUMN IF EXISTS groans;
+ALTER TABLE jokes DROP COLUMN IF EXISTS votes;
+DROP TABLE IF EXISTS ratings;
@@ -0,0 +1,14 @@
+-- Visitors' ratings of jokes, from 1 to 5 groans. A voter is a
+-- browser session or a registered user, with one rating per
+-- joke. Each joke keeps its vote count and groan total.
+
+CREATE TABLE IF NOT EXISTS ratings (
+  id TEXT NOT NULL REFERENCES jokes(id),
+  voter TEXT NOT NULL,
+  rating SMALLINT NOT NULL CHECK (rating BETWEEN 1 AND 5),
+  created TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (id, voter)
+);
+
+ALTER TABLE jokes ADD COLUMN IF NOT EXISTS votes INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE jokes ADD COLUMN IF NOT EXISTS groans INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,10 @@
+-- Each time a joke is told, and through which channel: the web
+-- front page, the API, or an integration named by an API client.
+
+CREATE TABLE IF NOT EXISTS views (
+  id TEXT NOT NULL,
+  channel TEXT NOT NULL,
+  served TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS views_id_served ON views (id, served);
@@ -0,0 +1,17 @@
+-- Named, ordered lists of jokes, such as a Halloween set.
+-- A joke can appear in a collection more than once.
+
+CREATE TABLE IF NOT EXISTS collections (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL,
+  description TEXT
+);
+
+CREATE TABLE IF NOT EXISTS collection_jokes (
+  collection TEXT NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+  position INTEGER NOT NULL,
+  id TEXT NOT NULL,
+  PRIMARY KEY (collection, position)
+);
+
+CREATE INDEX IF NOT EXISTS collection_jokes_id ON collection_jokes (id);
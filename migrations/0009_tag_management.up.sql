@@ -0,0 +1,15 @@
+-- Tags are lowercase, as the web form didn't always make them.
+-- Tags can have a description, and aliases: tags that are
+-- replaced by another whenever a joke is written.
+
+UPDATE tags SET tag = lower(btrim(tag));
+
+CREATE TABLE IF NOT EXISTS tag_descriptions (
+  tag TEXT PRIMARY KEY,
+  description TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tag_aliases (
+  alias TEXT PRIMARY KEY,
+  tag TEXT NOT NULL
+);
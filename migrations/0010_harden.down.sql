@@ -0,0 +1,15 @@
+DROP INDEX IF EXISTS jokes_deleted;
+ALTER TABLE jokes DROP COLUMN IF EXISTS updated;
+ALTER TABLE jokes DROP COLUMN IF EXISTS created;
+
+ALTER TABLE ratings DROP CONSTRAINT IF EXISTS ratings_id_fkey;
+ALTER TABLE ratings ADD CONSTRAINT ratings_id_fkey FOREIGN KEY (id) REFERENCES jokes(id);
+DROP INDEX IF EXISTS aliases_id;
+ALTER TABLE aliases DROP CONSTRAINT IF EXISTS aliases_id_fkey;
+ALTER TABLE aliases ADD CONSTRAINT aliases_id_fkey FOREIGN KEY (id) REFERENCES jokes(id);
+
+DROP INDEX IF EXISTS tags_tag;
+ALTER TABLE tags DROP CONSTRAINT IF EXISTS tags_id_fkey;
+ALTER TABLE tags ADD CONSTRAINT tags_id_fkey FOREIGN KEY (id) REFERENCES jokes(id);
+ALTER TABLE tags DROP CONSTRAINT IF EXISTS tags_pkey;
+ALTER TABLE tags ALTER COLUMN id DROP NOT NULL;
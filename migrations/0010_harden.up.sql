@@ -0,0 +1,42 @@
+-- Constraints the jokebase relied on without the database
+-- enforcing them, and when each joke was added and last
+-- changed.
+
+-- Tags belong to a joke, each at most once, and go with their
+-- joke when it is deleted or renamed. Repeats left by earlier
+-- versions (including ones made by lowercasing) are dropped.
+DELETE FROM tags WHERE id IS NULL;
+DELETE FROM tags t USING tags u
+  WHERE t.id = u.id AND t.tag = u.tag AND t.ctid > u.ctid;
+ALTER TABLE tags ALTER COLUMN id SET NOT NULL;
+ALTER TABLE tags DROP CONSTRAINT IF EXISTS tags_pkey;
+ALTER TABLE tags ADD CONSTRAINT tags_pkey PRIMARY KEY (id, tag);
+ALTER TABLE tags DROP CONSTRAINT IF EXISTS tags_id_fkey;
+ALTER TABLE tags ADD CONSTRAINT tags_id_fkey
+  FOREIGN KEY (id) REFERENCES jokes(id) ON DELETE CASCADE ON UPDATE CASCADE;
+CREATE INDEX IF NOT EXISTS tags_tag ON tags (tag);
+
+-- Aliases and ratings follow their joke the same way.
+ALTER TABLE aliases DROP CONSTRAINT IF EXISTS aliases_id_fkey;
+ALTER TABLE aliases ADD CONSTRAINT aliases_id_fkey
+  FOREIGN KEY (id) REFERENCES jokes(id) ON DELETE CASCADE ON UPDATE CASCADE;
+CREATE INDEX IF NOT EXISTS aliases_id ON aliases (id);
+ALTER TABLE ratings DROP CONSTRAINT IF EXISTS ratings_id_fkey;
+ALTER TABLE ratings ADD CONSTRAINT ratings_id_fkey
+  FOREIGN KEY (id) REFERENCES jokes(id) ON DELETE CASCADE ON UPDATE CASCADE;
+
+-- Creation and update times, from the joke's revisions where
+-- it has any.
+ALTER TABLE jokes ADD COLUMN IF NOT EXISTS created TIMESTAMPTZ;
+ALTER TABLE jokes ADD COLUMN IF NOT EXISTS updated TIMESTAMPTZ;
+UPDATE jokes j SET (created, updated) = (
+  SELECT coalesce(j.created, min(r.created), now()), coalesce(j.updated, max(r.created), now())
+  FROM revisions r WHERE r.id = j.id
+);
+ALTER TABLE jokes ALTER COLUMN created SET DEFAULT now();
+ALTER TABLE jokes ALTER COLUMN created SET NOT NULL;
+ALTER TABLE jokes ALTER COLUMN updated SET DEFAULT now();
+ALTER TABLE jokes ALTER COLUMN updated SET NOT NULL;
+
+-- Trash purges look for old deletions.
+CREATE INDEX IF NOT EXISTS jokes_deleted ON jokes (deleted) WHERE deleted IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Jokes that don't fit the classic five lines can be told from
+-- a script: a JSON array of turns, each with a speaker and a
+-- line.
+
+ALTER TABLE jokes ADD COLUMN IF NOT EXISTS script JSONB;
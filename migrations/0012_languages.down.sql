@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS jokes_translation_of;
+ALTER TABLE jokes DROP COLUMN IF EXISTS translation_of;
+ALTER TABLE jokes DROP COLUMN IF EXISTS lang;
@@ -0,0 +1,9 @@
+-- The language each joke is written in, and for translations
+-- the joke they translate. Checked at the end of a transaction
+-- so that a restore can load translations before originals.
+
+ALTER TABLE jokes ADD COLUMN IF NOT EXISTS lang TEXT NOT NULL DEFAULT 'en';
+ALTER TABLE jokes ADD COLUMN IF NOT EXISTS translation_of TEXT
+  REFERENCES jokes(id) ON DELETE SET NULL ON UPDATE CASCADE
+  DEFERRABLE INITIALLY DEFERRED;
+CREATE INDEX IF NOT EXISTS jokes_translation_of ON jokes (translation_of);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS jokes_flagged;
+ALTER TABLE jokes DROP COLUMN IF EXISTS flagged;
+ALTER TABLE jokes DROP COLUMN IF EXISTS audience;
@@ -0,0 +1,8 @@
+-- Who each joke is fit for, and the words the wordlist flagged
+-- in it, waiting for review. Family-safe mode only tells
+-- all-ages jokes with nothing flagged.
+
+ALTER TABLE jokes ADD COLUMN IF NOT EXISTS audience TEXT NOT NULL DEFAULT 'all-ages'
+  CONSTRAINT jokes_audience CHECK (audience IN ('all-ages', 'teen', 'adult'));
+ALTER TABLE jokes ADD COLUMN IF NOT EXISTS flagged TEXT[] NOT NULL DEFAULT '{}';
+CREATE INDEX IF NOT EXISTS jokes_flagged ON jokes (id) WHERE cardinality(flagged) > 0;
@@ -0,0 +1,2 @@
+ALTER TABLE collection_jokes DROP CONSTRAINT IF EXISTS collection_jokes_id_fkey;
+ALTER TABLE views DROP CONSTRAINT IF EXISTS views_id_fkey;
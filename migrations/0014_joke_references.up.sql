@@ -0,0 +1,14 @@
+-- Views and collection entries belong to a joke the way its
+-- tags do: they go with it when it is purged or restored away,
+-- and follow it when it is renamed. Rows left behind by jokes
+-- already gone are dropped.
+
+DELETE FROM views v WHERE NOT EXISTS (SELECT 1 FROM jokes j WHERE j.id = v.id);
+ALTER TABLE views DROP CONSTRAINT IF EXISTS views_id_fkey;
+ALTER TABLE views ADD CONSTRAINT views_id_fkey
+  FOREIGN KEY (id) REFERENCES jokes(id) ON DELETE CASCADE ON UPDATE CASCADE;
+
+DELETE FROM collection_jokes c WHERE NOT EXISTS (SELECT 1 FROM jokes j WHERE j.id = c.id);
+ALTER TABLE collection_jokes DROP CONSTRAINT IF EXISTS collection_jokes_id_fkey;
+ALTER TABLE collection_jokes ADD CONSTRAINT collection_jokes_id_fkey
+  FOREIGN KEY (id) REFERENCES jokes(id) ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1,147 @@
+//! Counts of jokes told. Serving a joke only queues a view;
+//! a background task writes queued views to the database in
+//! batches, so that counting never slows down telling.
+
+use crate::*;
+
+/// Views waiting to be written before new ones are dropped.
+const VIEW_QUEUE_SIZE: usize = 4096;
+/// Most views written at once.
+const VIEW_BATCH_SIZE: usize = 256;
+/// Furthest back, in days, analytics will count views.
+pub const MAX_ANALYTICS_DAYS: i64 = 10 * 366;
+
+/// Where a joke was told.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Channel {
+    /// The web front page.
+    Web,
+    /// The API, with no integration named.
+    Api,
+    /// An integration using the API that names itself with
+    /// `?via=`.
+    Integration(String),
+}
+
+impl Channel {
+    /// Channel for an API request, from its `via` parameter.
+    /// Names that are not short slugs count as plain API use.
+    pub fn api(via: Option<&str>) -> Self {
+        match via {
+            Some(via) if is_slug(via) && via.len() <= MAX_TAG_LEN && via != "web" => {
+                Channel::Integration(via.to_string())
+            }
+            _ => Channel::Api,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Channel::Web => "web",
+            Channel::Api => "api",
+            Channel::Integration(name) => name,
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ViewParams {
+    /// Name of the integration telling the joke, for analytics.
+    #[param(example = "slack")]
+    via: Option<String>,
+}
+
+impl ViewParams {
+    pub fn channel(&self) -> Channel {
+        Channel::api(self.via.as_deref())
+    }
+}
+
+/// One telling of a joke.
+#[derive(Debug, Clone)]
+pub struct View {
+    pub id: String,
+    pub channel: Channel,
+    pub served: DateTime<Utc>,
+}
+
+/// Queue of views for the background writer.
+#[derive(Clone)]
+pub struct ViewLog(mpsc::Sender<View>);
+
+impl ViewLog {
+    /// Start the background writer for views of jokes in the
+    /// given jokebase.
+    pub fn spawn(jokebase: JokeBase) -> Self {
+        let (sender, mut receiver) = mpsc::channel(VIEW_QUEUE_SIZE);
+        tokio::spawn(async move {
+            let mut views = Vec::with_capacity(VIEW_BATCH_SIZE);
+            while receiver.recv_many(&mut views, VIEW_BATCH_SIZE).await > 0 {
+                if let Err(e) = jokebase.record_views(&views).await {
+                    tracing::error!("analytics: dropped {} views: {}", views.len(), e);
+                }
+                views.clear();
+            }
+        });
+        ViewLog(sender)
+    }
+
+    /// Queue a view of a joke. Never waits: if the writer has
+    /// fallen behind the view is dropped.
+    pub fn record(&self, id: &str, channel: Channel) {
+        let view = View {
+            id: id.to_string(),
+            channel,
+            served: Utc::now(),
+        };
+        if let Err(mpsc::error::TrySendError::Full(view)) = self.0.try_send(view) {
+            tracing::warn!("analytics: queue full, dropped view of {}", view.id);
+        }
+    }
+}
+
+/// How often a joke was told in some period.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ViewCount {
+    #[schema(example = "boo")]
+    pub id: String,
+    #[schema(example = 42)]
+    pub total: i64,
+    /// Views by channel.
+    #[schema(example = json!({"web": 30, "api": 10, "slack": 2}))]
+    pub channels: BTreeMap<String, i64>,
+}
+
+/// Time series bucket size.
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Bucket {
+    Hour,
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl Bucket {
+    /// Postgres `date_trunc` field name.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Bucket::Hour => "hour",
+            Bucket::Day => "day",
+            Bucket::Week => "week",
+            Bucket::Month => "month",
+        }
+    }
+}
+
+/// Views of a joke through one channel in one time bucket.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ViewPoint {
+    /// Start of the bucket.
+    pub period: DateTime<Utc>,
+    #[schema(example = "web")]
+    pub channel: String,
+    #[schema(example = 12)]
+    pub views: i64,
+}
@@ -6,15 +6,57 @@ use crate::*;
 #[openapi(
     paths(
         jokes,
+        top_jokes,
+        import_jokes,
+        import_text_jokes,
+        export_jokes,
+        export_fortunes,
+        restore_jokes,
         joke,
         get_joke,
         post_joke,
         delete_joke,
         update_joke,
+        patch_joke,
+        edit_joke_tags,
+        rename_joke,
+        rate_joke,
+        related_jokes,
+        joke_translations,
+        joke_history,
+        restore_joke,
+        undelete_joke,
+        review_joke,
+        duplicate_jokes,
+        review_queue,
+        tags,
+        tags_report,
+        describe_tag,
+        rename_tag,
+        merge_tags,
+        tag_aliases,
+        set_tag_alias,
+        delete_tag_alias,
+        collections,
+        post_collection,
+        get_collection,
+        update_collection,
+        delete_collection,
+        collection_joke,
+        analytics,
+        analytics_report,
+        joke_analytics,
+        trash,
+        purge_trash,
         register,
     ),
     components(
-        schemas(Joke, JokeBaseError, AuthError)
+        schemas(Joke, NewJoke, Problem, Revision, RevisionAction, TrashedJoke, TagEdit, Rename,
+            ConflictPolicy, ImportStatus, ImportResult, DumpFormat, JokeRecord, VoteRecord,
+            TextJokes, TextImportReport, TextFormat, FieldError,
+            DuplicatePolicy, DuplicateCluster, Rating, Vote, RelatedJoke, ViewCount, ViewPoint, Bucket,
+            Collection, CollectionOrder, TagInfo, TagRename, TagMerge, TagDescription, TagAlias,
+            AliasTarget, Turn, Speaker, Audience, Review)
     ),
     tags(
         (name = "knock-knock", description = "Knock-Knock Joke API")
@@ -22,45 +64,515 @@ use crate::*;
 )]
 pub struct ApiDoc;
 
+/// True if the client's `If-None-Match` says it already has
+/// this version.
+fn not_modified(if_none_match: Option<TypedHeader<IfNoneMatch>>, etag: &ETag) -> bool {
+    matches!(if_none_match, Some(TypedHeader(inm)) if !inm.precondition_passes(etag))
+}
+
+fn not_modified_response(etag: ETag) -> Response {
+    let vary = [(header::VARY, FORMAT_VARY)];
+    (StatusCode::NOT_MODIFIED, vary, TypedHeader(etag)).into_response()
+}
+
+/// The request's `If-Match` header, if it has one, as the
+/// entity tags it lists. Taken from the headers directly: an
+/// absent `If-Match` would decode as an empty tag list, which
+/// matches nothing, and a typed one can't be compared by parts.
+pub struct IfMatchHeader(pub Option<Vec<String>>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IfMatchHeader {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if !parts.headers.contains_key(header::IF_MATCH) {
+            return Ok(IfMatchHeader(None));
+        }
+        // Decoding it checks that it is well formed.
+        let TypedHeader(_) = TypedHeader::<IfMatch>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let tags = parts
+            .headers
+            .get_all(header::IF_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        Ok(IfMatchHeader(Some(tags)))
+    }
+}
+
+/// True if `If-Match` names the joke's content in any of its
+/// formats, or is `*`.
+fn if_match_passes(tags: &[String], joke: &Joke) -> bool {
+    tags.iter()
+        .any(|tag| tag == "*" || joke.content_matches(tag))
+}
+
+/// Check an `If-Match` precondition against the current version
+/// of the joke, in any format. A joke that does not exist
+/// matches nothing.
+async fn check_if_match(
+    jokebase: &JokeBase,
+    joke_id: &str,
+    if_match: IfMatchHeader,
+) -> Result<(), JokeBaseErr> {
+    let IfMatchHeader(Some(if_match)) = if_match else {
+        return Ok(());
+    };
+    match jokebase.get(joke_id).await {
+        Ok(joke) if if_match_passes(&if_match, &joke) => Ok(()),
+        Ok(_) | Err(JokeBaseErr::JokeDoesNotExist(_)) | Err(JokeBaseErr::JokeRenamed(_)) => {
+            Err(JokeBaseErr::JokeChanged(joke_id.to_string()))
+        }
+        Err(e) => Err(e),
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/api/v1/jokes",
+    params(
+        AudienceParams,
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a list already held"),
+    ),
     responses(
-        (status = 200, description = "List jokes", body = [Joke])
+        (status = 200, description = "List jokes, in the format chosen by Accept",
+            content(
+                ("application/json" = [Joke]),
+                ("text/plain" = String),
+                ("text/html" = String),
+                ("text/markdown" = String),
+                ("application/yaml" = [Joke]),
+                ("text/csv" = String),
+            ),
+            headers(("ETag" = String, description = "Version of the list in this format"))),
+        (status = 304, description = "List unchanged since If-None-Match"),
+        (status = 406, description = "No acceptable format",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 503, description = "Database unavailable",
+            body = Problem, content_type = "application/problem+json"),
     )
 )]
-pub async fn jokes(State(appstate): HandlerAppState) -> Response {
-    let jokes = appstate.read().await.jokebase.get_jokes().await;
-    (StatusCode::OK, Json(jokes)).into_response()
+pub async fn jokes(
+    State(appstate): HandlerAppState,
+    Query(audience): Query<AudienceParams>,
+    format: JokeFormat,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
+) -> Response {
+    let appstate = appstate.read().await;
+    match appstate
+        .jokebase
+        .get_jokes(audience.family_safe(&appstate))
+        .await
+    {
+        Ok(jokes) => {
+            let etag = jokes_etag(&jokes, format);
+            if not_modified(if_none_match, &etag) {
+                return not_modified_response(etag);
+            }
+            (TypedHeader(etag), format.jokes_response(&jokes)).into_response()
+        }
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct TopParams {
+    /// Most jokes to list.
+    #[param(default = 10, maximum = 100)]
+    limit: Option<i64>,
+    /// Leave out jokes with fewer votes than this.
+    #[param(default = 1)]
+    min_votes: Option<i32>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/jokes/top",
+    params(TopParams, AudienceParams),
+    responses(
+        (status = 200, description = "Best rated jokes, best first", body = [Joke]),
+    )
+)]
+pub async fn top_jokes(
+    State(appstate): HandlerAppState,
+    Query(params): Query<TopParams>,
+    Query(audience): Query<AudienceParams>,
+) -> Response {
+    let limit = params.limit.unwrap_or(10).clamp(1, 100);
+    let min_votes = params.min_votes.unwrap_or(1);
+    let appstate = appstate.read().await;
+    let family_safe = audience.family_safe(&appstate);
+    match appstate
+        .jokebase
+        .top_rated(limit, min_votes, family_safe)
+        .await
+    {
+        Ok(jokes) => (StatusCode::OK, Json(jokes)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ImportParams {
+    /// What to do with jokes whose id is already in use.
+    #[param(inline)]
+    #[serde(default)]
+    policy: ConflictPolicy,
+    /// What to do with jokes that look like copies of ones
+    /// already there, instead of the server's setting.
+    #[param(inline)]
+    duplicates: Option<DuplicatePolicy>,
+    /// Commit after this many jokes rather than all at once.
+    batch_size: Option<usize>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/jokes/import",
+    params(ImportParams),
+    request_body(
+        content = [Joke],
+        description = "Jokes to import, as a JSON array or as NDJSON (`application/x-ndjson`)",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Outcome for each joke", body = [ImportResult]),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Unreadable jokes",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn import_jokes(
+    claims: Claims,
+    State(appstate): HandlerAppState,
+    Query(params): Query<ImportParams>,
+    body: String,
+) -> Response {
+    let jokes = match parse_jokes(&body) {
+        Ok(jokes) => jokes,
+        Err(e) => return Problem::response(e),
+    };
+    let mut appstate = appstate.write().await;
+    let duplicates = params.duplicates.unwrap_or(appstate.duplicates);
+    let results = appstate
+        .jokebase
+        .import(
+            &jokes,
+            params.policy,
+            duplicates,
+            params.batch_size,
+            claims.author(),
+        )
+        .await;
+    match results {
+        Ok(results) => (StatusCode::OK, Json(results)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct TextImportParams {
+    /// Layout of the text.
+    #[param(inline)]
+    #[serde(default)]
+    format: TextFormat,
+    /// Source to give the jokes that don't say.
+    #[param(example = "https://lajollamom.com/kid-friendly-knock-knock-jokes")]
+    source: Option<String>,
+    /// Only report what would be imported.
+    #[serde(default)]
+    dry_run: bool,
+    /// What to do with jokes whose id is already in use.
+    #[param(inline)]
+    #[serde(default)]
+    policy: ConflictPolicy,
+    /// What to do with jokes that look like copies of ones
+    /// already there, instead of the server's setting.
+    #[param(inline)]
+    duplicates: Option<DuplicatePolicy>,
+    /// Commit after this many jokes rather than all at once.
+    batch_size: Option<usize>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/jokes/import/text",
+    params(TextImportParams),
+    request_body(
+        content = String,
+        description = "Numbered plain-text jokes with `tag:` lines, or a fortune file",
+        content_type = "text/plain"
+    ),
+    responses(
+        (status = 200, description = "Jokes read, id collisions and import outcomes",
+            body = TextImportReport),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn import_text_jokes(
+    claims: Claims,
+    State(appstate): HandlerAppState,
+    Query(params): Query<TextImportParams>,
+    body: String,
+) -> Response {
+    let parsed = parse_text(&body, params.format, params.source.as_deref());
+    let mut appstate = appstate.write().await;
+    let duplicates = params.duplicates.unwrap_or(appstate.duplicates);
+    let report = appstate
+        .jokebase
+        .import_text(
+            parsed,
+            params.dry_run,
+            params.policy,
+            duplicates,
+            params.batch_size,
+            claims.author(),
+        )
+        .await;
+    match report {
+        Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ExportParams {
+    /// Layout of the dump.
+    #[param(inline)]
+    #[serde(default)]
+    format: DumpFormat,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/export",
+    params(ExportParams),
+    responses(
+        (status = 200, description = "All jokes, including trashed ones", body = [JokeRecord]),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn export_jokes(
+    _claims: Claims,
+    State(appstate): HandlerAppState,
+    Query(params): Query<ExportParams>,
+) -> Response {
+    match appstate.read().await.jokebase.export().await {
+        Ok(records) => {
+            let content_type = [(header::CONTENT_TYPE, params.format.content_type())];
+            (
+                StatusCode::OK,
+                content_type,
+                format_dump(&records, params.format),
+            )
+                .into_response()
+        }
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct FortuneParams {
+    /// Only export jokes with this tag.
+    #[param(example = "kids")]
+    tag: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/export/fortune",
+    params(FortuneParams, AudienceParams),
+    responses(
+        (status = 200, description = "Jokes as a fortune(6) file", body = String,
+            content_type = "text/plain"),
+    )
+)]
+pub async fn export_fortunes(
+    State(appstate): HandlerAppState,
+    Query(params): Query<FortuneParams>,
+    Query(audience): Query<AudienceParams>,
+) -> Response {
+    let appstate = appstate.read().await;
+    match appstate
+        .jokebase
+        .get_jokes(audience.family_safe(&appstate))
+        .await
+    {
+        Ok(jokes) => {
+            let fortunes = format_fortunes(&jokes, params.tag.as_deref());
+            (StatusCode::OK, fortunes).into_response()
+        }
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/restore",
+    request_body(
+        content = [JokeRecord],
+        description = "Dump to replace the jokebase with, in any export format",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Jokebase replaced", body = ()),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Unreadable or invalid dump; nothing was replaced",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn restore_jokes(
+    claims: Claims,
+    State(appstate): HandlerAppState,
+    body: String,
+) -> Response {
+    let records = match parse_dump(&body) {
+        Ok(records) => records,
+        Err(e) => return Problem::response(e),
+    };
+    let mut appstate = appstate.write().await;
+    match appstate.jokebase.load_dump(&records, claims.author()).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct RandomParams {
+    /// Favor better rated jokes.
+    #[serde(default)]
+    weighted: bool,
 }
 
 #[utoipa::path(
     get,
     path = "/api/v1/joke",
+    params(
+        RandomParams,
+        AudienceParams,
+        ViewParams,
+        LangParams,
+        ("Accept-Language" = Option<String>, Header, description = "Languages to tell the joke in"),
+    ),
     responses(
-        (status = 200, description = "Return random joke", body = Joke),
-        (status = 204, description = "Jokebase is empty", body = JokeBaseError)
+        (status = 200, description = "Return random joke, in the format chosen by Accept, \
+            translated if possible into the language chosen by lang or Accept-Language",
+            content(
+                ("application/json" = Joke),
+                ("text/plain" = String),
+                ("text/html" = String),
+                ("text/markdown" = String),
+                ("application/yaml" = Joke),
+                ("text/csv" = String),
+            ),
+            headers(("Content-Language" = String, description = "Language of the joke"))),
+        (status = 404, description = "Jokebase is empty",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 406, description = "No acceptable format",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 503, description = "Database unavailable",
+            body = Problem, content_type = "application/problem+json"),
     )
 )]
-pub async fn joke(State(appstate): HandlerAppState) -> Response {
-    match appstate.read().await.jokebase.get_random().await {
-        Ok(joke) => joke.into_response(),
-        Err(e) => JokeBaseError::response(StatusCode::NO_CONTENT, e),
+pub async fn joke(
+    State(appstate): HandlerAppState,
+    Query(params): Query<RandomParams>,
+    Query(audience): Query<AudienceParams>,
+    Query(view): Query<ViewParams>,
+    Query(lang): Query<LangParams>,
+    accepted: AcceptLanguage,
+    format: JokeFormat,
+) -> Response {
+    let appstate = appstate.read().await;
+    let langs = lang.preferences(accepted);
+    let family_safe = audience.family_safe(&appstate);
+    let joke = match appstate
+        .jokebase
+        .get_random(params.weighted, family_safe)
+        .await
+    {
+        Ok(joke) => appstate.jokebase.localize(joke, &langs, family_safe).await,
+        e => e,
+    };
+    match joke {
+        Ok(joke) => {
+            appstate.views.record(&joke.id, view.channel());
+            (language_headers(&joke), format.joke_response(&joke)).into_response()
+        }
+        Err(e) => Problem::response(e),
     }
 }
 
 #[utoipa::path(
     get,
     path = "/api/v1/joke/{id}",
+    params(
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a joke already held"),
+        AudienceParams,
+        ViewParams,
+    ),
     responses(
-        (status = 200, description = "Return specified joke", body = Joke),
-        (status = 204, description = "No joke with this id", body = JokeBaseError),
+        (status = 200, description = "Return specified joke, in the format chosen by Accept",
+            content(
+                ("application/json" = Joke),
+                ("text/plain" = String),
+                ("text/html" = String),
+                ("text/markdown" = String),
+                ("application/yaml" = Joke),
+                ("text/csv" = String),
+            ),
+            headers(
+                ("ETag" = String, description = "Version of the joke in this format"),
+                ("Content-Language" = String, description = "Language of the joke"),
+            )),
+        (status = 404, description = "No joke with this id, or none fit for family-safe mode",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 304, description = "Joke unchanged since If-None-Match"),
+        (status = 308, description = "Joke has been renamed",
+            headers(("Location" = String, description = "URL of joke under its new id"))),
+        (status = 406, description = "No acceptable format",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 503, description = "Database unavailable",
+            body = Problem, content_type = "application/problem+json"),
     )
 )]
-pub async fn get_joke(State(appstate): HandlerAppState, Path(joke_id): Path<String>) -> Response {
-    match appstate.read().await.jokebase.get(&joke_id).await {
-        Ok(joke) => joke.into_response(),
-        Err(e) => JokeBaseError::response(StatusCode::NO_CONTENT, e),
+pub async fn get_joke(
+    State(appstate): HandlerAppState,
+    Path(joke_id): Path<String>,
+    Query(audience): Query<AudienceParams>,
+    Query(view): Query<ViewParams>,
+    format: JokeFormat,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
+) -> Response {
+    let appstate = appstate.read().await;
+    let family_safe = audience.family_safe(&appstate);
+    let joke = appstate.jokebase.get(&joke_id).await;
+    match joke.and_then(|joke| check_family_safe(joke, family_safe)) {
+        Ok(joke) => {
+            let etag = joke.format_etag(format);
+            let content_language = [(header::CONTENT_LANGUAGE, joke.lang.clone())];
+            if not_modified(if_none_match, &etag) {
+                return (content_language, not_modified_response(etag)).into_response();
+            }
+            appstate.views.record(&joke.id, view.channel());
+            (
+                content_language,
+                TypedHeader(etag),
+                format.joke_response(&joke),
+            )
+                .into_response()
+        }
+        Err(e) => Problem::response(e),
     }
 }
 
@@ -68,41 +580,81 @@ pub async fn get_joke(State(appstate): HandlerAppState, Path(joke_id): Path<Stri
     post,
     path = "/api/v1/joke/add",
     request_body(
-        content = inline(Joke),
-        description = "Joke to add"
+        content = inline(NewJoke),
+        description = "Joke to add, with or without an id"
     ),
     responses(
-        (status = 201, description = "Added joke", body = ()),
-        (status = 400, description = "Bad request", body = JokeBaseError)
+        (status = 201, description = "Added joke, as stored", body = Joke,
+            headers(
+                ("Location" = String, description = "URL of the joke"),
+                ("ETag" = String, description = "Version of the joke"),
+                ("Link" = String, description = "Jokes this one looks like a copy of, \
+                    as `rel=\"duplicate\"` links"),
+            )),
+        (status = 409, description = "Joke already exists, or looks like a copy of one",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Joke is invalid",
+            body = Problem, content_type = "application/problem+json"),
     )
 )]
 pub async fn post_joke(
-    _claims: Claims,
+    claims: Claims,
     State(appstate): HandlerAppState,
-    Json(joke): Json<Joke>,
+    Json(joke): Json<NewJoke>,
 ) -> Response {
-    match appstate.write().await.jokebase.add(joke).await {
-        Ok(()) => StatusCode::CREATED.into_response(),
-        Err(e) => JokeBaseError::response(StatusCode::BAD_REQUEST, e),
+    let mut appstate = appstate.write().await;
+    let duplicates = appstate.duplicates;
+    match appstate
+        .jokebase
+        .add(Joke::from(joke), duplicates, claims.author())
+        .await
+    {
+        Ok((joke, similar)) => {
+            let mut headers = vec![(header::LOCATION, format!("/api/v1/joke/{}", joke.id))];
+            for id in similar {
+                let link = format!("</api/v1/joke/{}>; rel=\"duplicate\"", id);
+                headers.push((header::LINK, link));
+            }
+            let headers = AppendHeaders(headers);
+            (
+                StatusCode::CREATED,
+                headers,
+                TypedHeader(joke.etag()),
+                &joke,
+            )
+                .into_response()
+        }
+        Err(e) => Problem::response(e),
     }
 }
 
 #[utoipa::path(
     delete,
     path = "/api/v1/joke/{id}",
+    params(
+        ("If-Match" = Option<String>, Header, description = "Only delete if joke has this ETag"),
+    ),
     responses(
-        (status = 200, description = "Deleted joke", body = ()),
-        (status = 400, description = "Bad request", body = JokeBaseError),
+        (status = 200, description = "Moved joke to trash", body = ()),
+        (status = 404, description = "Joke not found",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Joke changed since it was read",
+            body = Problem, content_type = "application/problem+json"),
     )
 )]
 pub async fn delete_joke(
-    _claims: Claims,
+    claims: Claims,
     State(appstate): HandlerAppState,
     Path(joke_id): Path<String>,
+    if_match: IfMatchHeader,
 ) -> Response {
-    match appstate.write().await.jokebase.delete(&joke_id).await {
+    let mut appstate = appstate.write().await;
+    if let Err(e) = check_if_match(&appstate.jokebase, &joke_id, if_match).await {
+        return Problem::response(e);
+    }
+    match appstate.jokebase.delete(&joke_id, claims.author()).await {
         Ok(()) => StatusCode::OK.into_response(),
-        Err(e) => JokeBaseError::response(StatusCode::BAD_REQUEST, e),
+        Err(e) => Problem::response(e),
     }
 }
 
@@ -113,29 +665,1016 @@ pub async fn delete_joke(
         content = inline(Joke),
         description = "Joke to update"
     ),
+    params(
+        ("If-Match" = Option<String>, Header, description = "Only update if joke has this ETag"),
+    ),
     responses(
-        (status = 200, description = "Updated joke", body = ()),
-        (status = 400, description = "Bad request", body = JokeBaseError),
-        (status = 404, description = "Joke not found", body = JokeBaseError),
-        (status = 422, description = "Unprocessable entity", body = JokeBaseError),
+        (status = 200, description = "Updated joke", body = (),
+            headers(("ETag" = String, description = "New version of the joke"))),
+        (status = 400, description = "Bad request",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Joke not found",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Joke changed since it was read",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Unprocessable entity",
+            body = Problem, content_type = "application/problem+json"),
     )
 )]
 pub async fn update_joke(
-    _claims: Claims,
+    claims: Claims,
     State(appstate): HandlerAppState,
     Path(joke_id): Path<String>,
+    if_match: IfMatchHeader,
     Json(joke): Json<Joke>,
 ) -> Response {
-    match appstate.write().await.jokebase.update(&joke_id, joke).await {
-        Ok(_) => StatusCode::OK.into_response(),
-        Err(JokeBaseErr::JokeUnprocessable(e)) => JokeBaseError::response(
-            StatusCode::UNPROCESSABLE_ENTITY,
-            JokeBaseErr::JokeUnprocessable(e),
-        ),
-        Err(JokeBaseErr::NoJoke) => {
-            JokeBaseError::response(StatusCode::NOT_FOUND, JokeBaseErr::NoJoke)
+    let mut appstate = appstate.write().await;
+    if let Err(e) = check_if_match(&appstate.jokebase, &joke_id, if_match).await {
+        return Problem::response(e);
+    }
+    match appstate
+        .jokebase
+        .update(&joke_id, joke, claims.author())
+        .await
+    {
+        Ok(joke) => (StatusCode::OK, TypedHeader(joke.etag())).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/joke/{id}",
+    request_body(
+        content = Object,
+        content_type = "application/merge-patch+json",
+        description = "JSON Merge Patch (RFC 7396) to apply to joke"
+    ),
+    params(
+        ("If-Match" = Option<String>, Header, description = "Only patch if joke has this ETag"),
+    ),
+    responses(
+        (status = 200, description = "Patched joke", body = Joke,
+            headers(("ETag" = String, description = "New version of the joke"))),
+        (status = 404, description = "Joke not found",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Joke changed since it was read",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Patch does not give a valid joke",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn patch_joke(
+    claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(joke_id): Path<String>,
+    if_match: IfMatchHeader,
+    Json(patch): Json<serde_json::Value>,
+) -> Response {
+    let mut appstate = appstate.write().await;
+    if let Err(e) = check_if_match(&appstate.jokebase, &joke_id, if_match).await {
+        return Problem::response(e);
+    }
+    let joke = match appstate.jokebase.get(&joke_id).await {
+        Ok(joke) => joke,
+        Err(e) => return Problem::response(e),
+    };
+    let joke = match apply_joke_patch(&joke, &patch) {
+        Ok(joke) => joke,
+        Err(e) => return Problem::response(e),
+    };
+    match appstate
+        .jokebase
+        .update(&joke_id, joke, claims.author())
+        .await
+    {
+        Ok(joke) => (TypedHeader(joke.etag()), &joke).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/joke/{id}/tags",
+    request_body(
+        content = TagEdit,
+        description = "Tags to add to and remove from joke"
+    ),
+    params(
+        ("If-Match" = Option<String>, Header, description = "Only edit if joke has this ETag"),
+    ),
+    responses(
+        (status = 200, description = "Joke with edited tags", body = Joke,
+            headers(("ETag" = String, description = "New version of the joke"))),
+        (status = 404, description = "Joke not found",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Joke changed since it was read",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Too many or malformed tags",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn edit_joke_tags(
+    claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(joke_id): Path<String>,
+    if_match: IfMatchHeader,
+    Json(edit): Json<TagEdit>,
+) -> Response {
+    let mut appstate = appstate.write().await;
+    if let Err(e) = check_if_match(&appstate.jokebase, &joke_id, if_match).await {
+        return Problem::response(e);
+    }
+    match appstate
+        .jokebase
+        .edit_tags(&joke_id, &edit, claims.author())
+        .await
+    {
+        Ok(joke) => (TypedHeader(joke.etag()), &joke).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct Rename {
+    /// New id for the joke.
+    #[schema(example = "europe")]
+    id: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/joke/{id}/rename",
+    request_body(
+        content = Rename,
+        description = "New id for joke"
+    ),
+    params(
+        ("If-Match" = Option<String>, Header, description = "Only rename if joke has this ETag"),
+    ),
+    responses(
+        (status = 200, description = "Renamed joke", body = Joke,
+            headers(("ETag" = String, description = "New version of the joke"))),
+        (status = 409, description = "New id already in use by a joke or as an old id of one",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Joke not found",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Joke changed since it was read",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "New id is not a valid slug",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn rename_joke(
+    claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(joke_id): Path<String>,
+    if_match: IfMatchHeader,
+    Json(rename): Json<Rename>,
+) -> Response {
+    let mut appstate = appstate.write().await;
+    if let Err(e) = check_if_match(&appstate.jokebase, &joke_id, if_match).await {
+        return Problem::response(e);
+    }
+    match appstate
+        .jokebase
+        .rename(&joke_id, &rename.id, claims.author())
+        .await
+    {
+        Ok(joke) => (TypedHeader(joke.etag()), &joke).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/joke/{id}/rating",
+    request_body(
+        content = Vote,
+        description = "Groans for the joke. Registered users vote once per joke, \
+            anyone else once per session; voting again replaces the earlier vote."
+    ),
+    responses(
+        (status = 200, description = "Joke with its new rating", body = Joke),
+        (status = 404, description = "Joke not found",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Rating out of range",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn rate_joke(
+    claims: Option<Claims>,
+    session: Session,
+    State(appstate): HandlerAppState,
+    Path(joke_id): Path<String>,
+    Json(vote): Json<Vote>,
+) -> Response {
+    let voter = match claims {
+        Some(claims) => user_voter(&claims),
+        None => match session_voter(&session).await {
+            Ok(voter) => voter,
+            Err(e) => return Problem::response(e),
+        },
+    };
+    let mut appstate = appstate.write().await;
+    match appstate.jokebase.rate(&joke_id, &voter, vote.rating).await {
+        Ok(joke) => joke.into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct RelatedParams {
+    /// Most jokes to list.
+    #[param(default = 5, maximum = 50)]
+    limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/joke/{id}/related",
+    params(RelatedParams, AudienceParams),
+    responses(
+        (status = 200, description = "Jokes most like this one by tags, source and wording, \
+            best first", body = [RelatedJoke]),
+        (status = 404, description = "Joke not found",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 308, description = "Joke has been renamed",
+            headers(("Location" = String, description = "URL of joke under its new id"))),
+    )
+)]
+pub async fn related_jokes(
+    State(appstate): HandlerAppState,
+    Path(joke_id): Path<String>,
+    Query(params): Query<RelatedParams>,
+    Query(audience): Query<AudienceParams>,
+) -> Response {
+    let limit = params.limit.unwrap_or(5).clamp(1, 50);
+    let appstate = appstate.read().await;
+    let family_safe = audience.family_safe(&appstate);
+    match appstate
+        .jokebase
+        .related(&joke_id, limit, family_safe)
+        .await
+    {
+        Ok(related) => (StatusCode::OK, Json(related)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/joke/{id}/translations",
+    params(AudienceParams),
+    responses(
+        (status = 200, description = "Joke in every language it has been written in, \
+            the original first", body = [Joke]),
+        (status = 404, description = "Joke not found",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 308, description = "Joke has been renamed",
+            headers(("Location" = String, description = "URL of joke under its new id"))),
+    )
+)]
+pub async fn joke_translations(
+    State(appstate): HandlerAppState,
+    Path(joke_id): Path<String>,
+    Query(audience): Query<AudienceParams>,
+) -> Response {
+    let appstate = appstate.read().await;
+    let family_safe = audience.family_safe(&appstate);
+    match appstate.jokebase.translations(&joke_id, family_safe).await {
+        Ok(jokes) => (StatusCode::OK, Json(jokes)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/joke/{id}/history",
+    responses(
+        (status = 200, description = "Revisions of joke, oldest first", body = [Revision]),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Joke not found",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn joke_history(
+    _claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(joke_id): Path<String>,
+) -> Response {
+    match appstate.read().await.jokebase.history(&joke_id).await {
+        Ok(revisions) => (StatusCode::OK, Json(revisions)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/joke/{id}/history/{revision}/restore",
+    responses(
+        (status = 200, description = "Restored joke", body = Joke),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Revision not found",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Revision has no content",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn restore_joke(
+    claims: Claims,
+    State(appstate): HandlerAppState,
+    Path((joke_id, revision)): Path<(String, i32)>,
+) -> Response {
+    let mut appstate = appstate.write().await;
+    match appstate
+        .jokebase
+        .restore(&joke_id, revision, claims.author())
+        .await
+    {
+        Ok(joke) => joke.into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/joke/{id}/restore",
+    responses(
+        (status = 200, description = "Joke taken out of trash", body = Joke),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Joke not in trash",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn undelete_joke(
+    claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(joke_id): Path<String>,
+) -> Response {
+    let mut appstate = appstate.write().await;
+    match appstate.jokebase.undelete(&joke_id, claims.author()).await {
+        Ok(joke) => joke.into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/trash",
+    responses(
+        (status = 200, description = "List jokes in trash", body = [TrashedJoke]),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn trash(_claims: Claims, State(appstate): HandlerAppState) -> Response {
+    match appstate.read().await.jokebase.get_trash().await {
+        Ok(trash) => (StatusCode::OK, Json(trash)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/duplicates",
+    responses(
+        (status = 200, description = "Groups of jokes that look like copies of each other",
+            body = [DuplicateCluster]),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn duplicate_jokes(_claims: Claims, State(appstate): HandlerAppState) -> Response {
+    match appstate.read().await.jokebase.find_duplicates().await {
+        Ok(clusters) => (StatusCode::OK, Json(clusters)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/review",
+    responses(
+        (status = 200, description = "Jokes with words beyond their audience, waiting for review, \
+            longest waiting first", body = [Joke]),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn review_queue(_claims: Claims, State(appstate): HandlerAppState) -> Response {
+    match appstate.read().await.jokebase.flagged_jokes().await {
+        Ok(jokes) => (StatusCode::OK, Json(jokes)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/joke/{id}/review",
+    request_body(
+        content = Review,
+        description = "Audience the joke is fit for"
+    ),
+    responses(
+        (status = 200, description = "Reviewed joke, no longer flagged", body = Joke),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Joke not found",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn review_joke(
+    claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(joke_id): Path<String>,
+    Json(review): Json<Review>,
+) -> Response {
+    let mut appstate = appstate.write().await;
+    match appstate
+        .jokebase
+        .review(&joke_id, review.audience, claims.author())
+        .await
+    {
+        Ok(joke) => (TypedHeader(joke.etag()), &joke).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections",
+    responses(
+        (status = 200, description = "All collections", body = [Collection]),
+    )
+)]
+pub async fn collections(State(appstate): HandlerAppState) -> Response {
+    match appstate.read().await.jokebase.get_collections().await {
+        Ok(collections) => (StatusCode::OK, Json(collections)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/collections",
+    request_body(content = inline(Collection), description = "Collection to add"),
+    responses(
+        (status = 201, description = "Added collection, as stored", body = Collection,
+            headers(("Location" = String, description = "URL of the collection"))),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Collection already exists",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid collection",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn post_collection(
+    _claims: Claims,
+    State(appstate): HandlerAppState,
+    Json(collection): Json<Collection>,
+) -> Response {
+    match appstate
+        .write()
+        .await
+        .jokebase
+        .add_collection(collection)
+        .await
+    {
+        Ok(collection) => {
+            let location = [(
+                header::LOCATION,
+                format!("/api/v1/collections/{}", collection.id),
+            )];
+            (StatusCode::CREATED, location, Json(collection)).into_response()
         }
-        Err(e) => JokeBaseError::response(StatusCode::BAD_REQUEST, e),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections/{id}",
+    responses(
+        (status = 200, description = "Collection", body = Collection),
+        (status = 404, description = "Collection not found",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_collection(
+    State(appstate): HandlerAppState,
+    Path(collection_id): Path<String>,
+) -> Response {
+    match appstate
+        .read()
+        .await
+        .jokebase
+        .get_collection(&collection_id)
+        .await
+    {
+        Ok(collection) => (StatusCode::OK, Json(collection)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/collections/{id}",
+    request_body(content = inline(Collection), description = "Collection to replace"),
+    responses(
+        (status = 200, description = "Updated collection, as stored", body = Collection),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Collection not found",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid collection",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn update_collection(
+    _claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(collection_id): Path<String>,
+    Json(collection): Json<Collection>,
+) -> Response {
+    let mut appstate = appstate.write().await;
+    match appstate
+        .jokebase
+        .update_collection(&collection_id, collection)
+        .await
+    {
+        Ok(collection) => (StatusCode::OK, Json(collection)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/collections/{id}",
+    responses(
+        (status = 200, description = "Deleted collection", body = ()),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Collection not found",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn delete_collection(
+    _claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(collection_id): Path<String>,
+) -> Response {
+    match appstate
+        .write()
+        .await
+        .jokebase
+        .delete_collection(&collection_id)
+        .await
+    {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct CollectionJokeParams {
+    /// Go through the collection in order, or pick at random.
+    #[param(inline)]
+    #[serde(default)]
+    order: CollectionOrder,
+    /// Position of the joke to tell in order, from 0.
+    #[serde(default)]
+    position: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/collections/{id}/joke",
+    params(CollectionJokeParams, AudienceParams, ViewParams),
+    responses(
+        (status = 200, description = "Joke from the collection, in the format chosen by Accept",
+            content(
+                ("application/json" = Joke),
+                ("text/plain" = String),
+                ("text/html" = String),
+                ("text/markdown" = String),
+                ("application/yaml" = Joke),
+                ("text/csv" = String),
+            ),
+            headers(("Link" = String, description = "URL of the next joke, in sequential order"))),
+        (status = 404, description = "Collection not found, or has no jokes",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 406, description = "No acceptable format",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn collection_joke(
+    State(appstate): HandlerAppState,
+    Path(collection_id): Path<String>,
+    Query(params): Query<CollectionJokeParams>,
+    Query(audience): Query<AudienceParams>,
+    Query(view): Query<ViewParams>,
+    format: JokeFormat,
+) -> Response {
+    let appstate = appstate.read().await;
+    let jokebase = &appstate.jokebase;
+    let family_safe = audience.family_safe(&appstate);
+    let joke = jokebase.collection_joke(&collection_id, params.order, params.position, family_safe);
+    match joke.await {
+        Ok((joke, position)) => {
+            appstate.views.record(&joke.id, view.channel());
+            let mut headers = Vec::new();
+            if params.order == CollectionOrder::Sequential {
+                let next = format!(
+                    "</api/v1/collections/{}/joke?position={}>; rel=\"next\"",
+                    collection_id,
+                    position + 1,
+                );
+                headers.push((header::LINK, next));
+            }
+            (AppendHeaders(headers), format.joke_response(&joke)).into_response()
+        }
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tags",
+    responses(
+        (status = 200, description = "Every tag, with how many live jokes have it",
+            body = [TagInfo]),
+    )
+)]
+pub async fn tags(State(appstate): HandlerAppState) -> Response {
+    match appstate.read().await.jokebase.tag_list().await {
+        Ok(tags) => (StatusCode::OK, Json(tags)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tags/report",
+    responses(
+        (status = 200, description = "Every tag, as an HTML table",
+            body = String, content_type = "text/html"),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn tags_report(_claims: Claims, State(appstate): HandlerAppState) -> Response {
+    match appstate.read().await.jokebase.tag_list().await {
+        Ok(tags) => (StatusCode::OK, TagsTemplate::new(&tags)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/tags/{tag}/description",
+    request_body(content = TagDescription, description = "Description of the tag"),
+    responses(
+        (status = 200, description = "Tag with its new description", body = TagInfo),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid tag or description",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn describe_tag(
+    _claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(tag): Path<String>,
+    Json(description): Json<TagDescription>,
+) -> Response {
+    let mut appstate = appstate.write().await;
+    match appstate
+        .jokebase
+        .describe_tag(&tag, description.description.as_deref())
+        .await
+    {
+        Ok(info) => (StatusCode::OK, Json(info)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/tags/{tag}/rename",
+    request_body(content = TagRename, description = "New name of the tag"),
+    responses(
+        (status = 200, description = "Tag under its new name", body = TagInfo),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Tag not found",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "New name already in use: merge instead",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid tag",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn rename_tag(
+    claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(tag): Path<String>,
+    Json(rename): Json<TagRename>,
+) -> Response {
+    let mut appstate = appstate.write().await;
+    match appstate
+        .jokebase
+        .rename_tag(&tag, &rename.to, claims.author())
+        .await
+    {
+        Ok(info) => (StatusCode::OK, Json(info)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/tags/merge",
+    request_body(content = TagMerge, description = "Tags to merge, and the tag to keep"),
+    responses(
+        (status = 200, description = "Tag the others were merged into", body = TagInfo),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Tag not found",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid tag",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn merge_tags(
+    claims: Claims,
+    State(appstate): HandlerAppState,
+    Json(merge): Json<TagMerge>,
+) -> Response {
+    let mut appstate = appstate.write().await;
+    match appstate.jokebase.merge_tags(&merge, claims.author()).await {
+        Ok(info) => (StatusCode::OK, Json(info)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tags/aliases",
+    responses(
+        (status = 200, description = "Tags replaced by others when jokes are written",
+            body = [TagAlias]),
+    )
+)]
+pub async fn tag_aliases(State(appstate): HandlerAppState) -> Response {
+    match appstate.read().await.jokebase.tag_aliases().await {
+        Ok(aliases) => (StatusCode::OK, Json(aliases)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/tags/aliases/{alias}",
+    request_body(content = AliasTarget, description = "Tag the alias stands for"),
+    responses(
+        (status = 200, description = "Alias as stored", body = TagAlias),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Alias is a tag in use: merge instead",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid tag",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn set_tag_alias(
+    _claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(alias): Path<String>,
+    Json(target): Json<AliasTarget>,
+) -> Response {
+    match appstate
+        .write()
+        .await
+        .jokebase
+        .set_tag_alias(&alias, &target.tag)
+        .await
+    {
+        Ok(alias) => (StatusCode::OK, Json(alias)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/tags/aliases/{alias}",
+    responses(
+        (status = 200, description = "Deleted alias", body = ()),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Alias not found",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn delete_tag_alias(
+    _claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(alias): Path<String>,
+) -> Response {
+    match appstate
+        .write()
+        .await
+        .jokebase
+        .delete_tag_alias(&alias)
+        .await
+    {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct AnalyticsParams {
+    /// Count views from this many days back.
+    #[param(default = 30, minimum = 0, maximum = 3660)]
+    days: Option<i64>,
+    /// Most jokes to list.
+    #[param(default = 100)]
+    limit: Option<i64>,
+}
+
+impl AnalyticsParams {
+    fn days(&self) -> i64 {
+        self.days.unwrap_or(30)
+    }
+
+    fn since(&self) -> Result<DateTime<Utc>, JokeBaseErr> {
+        days_ago("days", self.days(), MAX_ANALYTICS_DAYS)
+    }
+
+    fn limit(&self) -> i64 {
+        self.limit.unwrap_or(100).max(1)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics",
+    params(AnalyticsParams),
+    responses(
+        (status = 200, description = "How often each joke was told, most told first",
+            body = [ViewCount]),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Number of days out of range",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn analytics(
+    _claims: Claims,
+    State(appstate): HandlerAppState,
+    Query(params): Query<AnalyticsParams>,
+) -> Response {
+    let since = match params.since() {
+        Ok(since) => since,
+        Err(e) => return Problem::response(e),
+    };
+    match appstate
+        .read()
+        .await
+        .jokebase
+        .view_counts(since, params.limit())
+        .await
+    {
+        Ok(counts) => (StatusCode::OK, Json(counts)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics/report",
+    params(AnalyticsParams),
+    responses(
+        (status = 200, description = "How often each joke was told, as an HTML table",
+            body = String, content_type = "text/html"),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Number of days out of range",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn analytics_report(
+    _claims: Claims,
+    State(appstate): HandlerAppState,
+    Query(params): Query<AnalyticsParams>,
+) -> Response {
+    let since = match params.since() {
+        Ok(since) => since,
+        Err(e) => return Problem::response(e),
+    };
+    match appstate
+        .read()
+        .await
+        .jokebase
+        .view_counts(since, params.limit())
+        .await
+    {
+        Ok(counts) => (
+            StatusCode::OK,
+            AnalyticsTemplate::new(&counts, params.days()),
+        )
+            .into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct SeriesParams {
+    /// Count views from this many days back.
+    #[param(default = 30, minimum = 0, maximum = 3660)]
+    days: Option<i64>,
+    /// Length of each period counted.
+    #[param(inline)]
+    #[serde(default)]
+    bucket: Bucket,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/joke/{id}/analytics",
+    params(SeriesParams),
+    responses(
+        (status = 200, description = "Views of joke by period and channel, oldest first",
+            body = [ViewPoint]),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Number of days out of range",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn joke_analytics(
+    _claims: Claims,
+    State(appstate): HandlerAppState,
+    Path(joke_id): Path<String>,
+    Query(params): Query<SeriesParams>,
+) -> Response {
+    let since = match days_ago("days", params.days.unwrap_or(30), MAX_ANALYTICS_DAYS) {
+        Ok(since) => since,
+        Err(e) => return Problem::response(e),
+    };
+    let series = appstate
+        .read()
+        .await
+        .jokebase
+        .view_series(&joke_id, params.bucket, since)
+        .await;
+    match series {
+        Ok(series) => (StatusCode::OK, Json(series)).into_response(),
+        Err(e) => Problem::response(e),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct PurgeParams {
+    /// Purge jokes trashed more than this many days ago,
+    /// instead of using the server's retention period.
+    #[param(minimum = 0, maximum = 36600)]
+    older_than_days: Option<i64>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/trash/purge",
+    params(PurgeParams),
+    responses(
+        (status = 200, description = "Ids of purged jokes", body = [String]),
+        (status = 401, description = "Unauthorized",
+            body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Number of days out of range",
+            body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn purge_trash(
+    _claims: Claims,
+    State(appstate): HandlerAppState,
+    Query(params): Query<PurgeParams>,
+) -> Response {
+    let mut appstate = appstate.write().await;
+    let before = match params.older_than_days {
+        Some(days) => match days_ago("older_than_days", days, MAX_RETENTION_DAYS) {
+            Ok(before) => before,
+            Err(e) => return Problem::response(e),
+        },
+        None => Utc::now() - appstate.trash_retention,
+    };
+    match appstate.jokebase.purge_trash(before).await {
+        Ok(ids) => (StatusCode::OK, Json(ids)).into_response(),
+        Err(e) => Problem::response(e),
     }
 }
 
@@ -148,7 +1687,8 @@ pub async fn update_joke(
     ),
     responses(
         (status = 200, description = "JSON Web Token", body = AuthBody),
-        (status = 401, description = "Registration failed", body = AuthError),
+        (status = 401, description = "Registration failed",
+            body = Problem, content_type = "application/problem+json"),
     )
 )]
 pub async fn register(
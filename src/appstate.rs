@@ -4,6 +4,11 @@ pub struct AppState {
     pub jokebase: JokeBase,
     pub jwt_keys: JwtKeys,
     pub reg_key: String,
+    pub trash_retention: TimeDelta,
+    pub duplicates: DuplicatePolicy,
+    /// Only tell and list jokes fit for children.
+    pub family_safe: bool,
+    pub views: ViewLog,
 }
 
 pub type SharedAppState = Arc<RwLock<AppState>>;
@@ -11,11 +16,23 @@ pub type SharedAppState = Arc<RwLock<AppState>>;
 pub type HandlerAppState = State<SharedAppState>;
 
 impl AppState {
-    pub fn new(jokebase: JokeBase, jwt_keys: JwtKeys, reg_key: String) -> Self {
+    pub fn new(
+        jokebase: JokeBase,
+        jwt_keys: JwtKeys,
+        reg_key: String,
+        trash_retention: TimeDelta,
+        duplicates: DuplicatePolicy,
+        family_safe: bool,
+        views: ViewLog,
+    ) -> Self {
         Self {
             jokebase,
             jwt_keys,
             reg_key,
+            trash_retention,
+            duplicates,
+            family_safe,
+            views,
         }
     }
 }
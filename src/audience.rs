@@ -0,0 +1,132 @@
+//! Who a joke is fit for, and a wordlist check that flags
+//! jokes using words beyond their audience for review. In
+//! family-safe mode only all-ages jokes that are not waiting
+//! for review are told or listed.
+
+use crate::*;
+
+use std::sync::OnceLock;
+
+/// Who a joke is fit for, youngest first.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Audience {
+    /// Fine for anyone, children included.
+    #[default]
+    AllAges,
+    /// Fine for teenagers.
+    Teen,
+    /// Only for grown-ups.
+    Adult,
+}
+
+impl Audience {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Audience::AllAges => "all-ages",
+            Audience::Teen => "teen",
+            Audience::Adult => "adult",
+        }
+    }
+}
+
+impl std::str::FromStr for Audience {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all-ages" => Ok(Audience::AllAges),
+            "teen" => Ok(Audience::Teen),
+            "adult" => Ok(Audience::Adult),
+            _ => Err(format!("unknown audience: {}", s)),
+        }
+    }
+}
+
+/// Words and the audience they are fit for, one per line as
+/// `word audience`. A word ending in `*` stands for any word
+/// starting with it.
+const WORDLIST: &str = include_str!("../assets/wordlist.txt");
+
+static WORDS: OnceLock<Vec<(String, Audience)>> = OnceLock::new();
+
+fn words() -> &'static [(String, Audience)] {
+    WORDS.get_or_init(|| parse_wordlist(WORDLIST))
+}
+
+fn parse_wordlist(text: &str) -> Vec<(String, Audience)> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (word, audience) = line.rsplit_once(char::is_whitespace)?;
+            Some((word.trim().to_lowercase(), audience.parse().ok()?))
+        })
+        .collect()
+}
+
+/// The audience a word is fit for, if the wordlist has it.
+fn word_audience(word: &str) -> Option<Audience> {
+    words()
+        .iter()
+        .filter(|(entry, _)| match entry.strip_suffix('*') {
+            Some(stem) => word.starts_with(stem),
+            None => word == entry,
+        })
+        .map(|(_, audience)| *audience)
+        .max()
+}
+
+/// Words of the joke, sorted, that the wordlist says are not
+/// fit for its audience.
+pub fn flag_words(joke: &Joke) -> Vec<String> {
+    let mut texts: Vec<&str> = vec![&joke.whos_there, &joke.answer_who];
+    texts.extend(joke.script.iter().flatten().map(|turn| turn.line.as_str()));
+    texts.extend(joke.tags.iter().flatten().map(String::as_str));
+    let words: BTreeSet<String> = texts
+        .iter()
+        .flat_map(|text| {
+            normalize_text(text)
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<String>>()
+        })
+        .filter(|word| word_audience(word).is_some_and(|audience| audience > joke.audience))
+        .collect();
+    words.into_iter().collect()
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct AudienceParams {
+    /// Only tell or list all-ages jokes not waiting for review.
+    /// Can turn family-safe mode on, but not off when the server
+    /// is run in it.
+    #[serde(default)]
+    family_safe: bool,
+}
+
+impl AudienceParams {
+    /// Whether this request is in family-safe mode, by itself or
+    /// because the server is.
+    pub fn family_safe(&self, appstate: &AppState) -> bool {
+        self.family_safe || appstate.family_safe
+    }
+}
+
+/// Fail as if the joke were not there if it is not to be told
+/// in family-safe mode.
+pub fn check_family_safe(joke: Joke, family_safe: bool) -> Result<Joke, JokeBaseErr> {
+    if family_safe && !joke.is_family_safe() {
+        return Err(JokeBaseErr::JokeDoesNotExist(joke.id));
+    }
+    Ok(joke)
+}
+
+/// A reviewer's verdict on a joke: the audience it is fit for.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct Review {
+    #[schema(example = "teen")]
+    pub audience: Audience,
+}
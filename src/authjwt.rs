@@ -2,7 +2,7 @@
 
 use crate::*;
 
-use jsonwebtoken::{EncodingKey, DecodingKey};
+use jsonwebtoken::{DecodingKey, EncodingKey};
 
 pub struct JwtKeys {
     encoding: EncodingKey,
@@ -39,15 +39,6 @@ pub enum AuthError {
     Registration,
 }
 
-impl<'s> ToSchema<'s> for AuthError {
-    fn schema() -> (&'s str, RefOr<Schema>) {
-        let example = serde_json::json!({
-            "status":"401","error":"wrong credentials"
-        });
-        error_schema("AuthError", example)
-    }
-}
-
 #[derive(Debug, Serialize, ToSchema)]
 pub struct AuthBody {
     access_token: String,
@@ -81,8 +72,11 @@ pub struct AuthPayload {
 impl FromRequestParts<SharedAppState> for Claims {
     type Rejection = AuthError;
 
-    async fn from_request_parts(parts: &mut Parts, state: &SharedAppState) -> Result<Self, Self::Rejection> {
-        use jsonwebtoken::{Algorithm, Validation, decode};
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &SharedAppState,
+    ) -> Result<Self, Self::Rejection> {
+        use jsonwebtoken::{decode, Algorithm, Validation};
 
         // Extract the token from the authorization header
         let TypedHeader(Authorization(bearer)) = parts
@@ -93,11 +87,7 @@ impl FromRequestParts<SharedAppState> for Claims {
         let appstate = state.read().await;
         let decoding_key = &appstate.jwt_keys.decoding;
         let validation = Validation::new(Algorithm::HS512);
-        let result = decode::<Claims>(
-            bearer.token(),
-            decoding_key,
-            &validation,
-        );
+        let result = decode::<Claims>(bearer.token(), decoding_key, &validation);
         let token_data = result.map_err(|_| AuthError::Registration)?;
         Ok(token_data.claims)
     }
@@ -105,16 +95,20 @@ impl FromRequestParts<SharedAppState> for Claims {
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AuthError::Registration => (StatusCode::UNAUTHORIZED, "Invalid registration"),
-            AuthError::TokenCreation => (StatusCode::INTERNAL_SERVER_ERROR, "Token creation error"),
-            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
+        let (status, kind, title) = match self {
+            AuthError::Registration => (
+                StatusCode::UNAUTHORIZED,
+                "registration",
+                "Invalid registration",
+            ),
+            AuthError::TokenCreation => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "token-creation",
+                "Token creation error",
+            ),
+            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "invalid-token", "Invalid token"),
         };
-        let body = Json(serde_json::json!({
-            "status": status.as_u16(),
-            "error": error_message,
-        }));
-        (status, body).into_response()
+        Problem::new(status, kind, title, self.to_string()).into_response()
     }
 }
 
@@ -138,9 +132,19 @@ pub struct Claims {
     exp: u64,
 }
 
-pub fn make_jwt_token(appstate: &AppState, registration: &Registration) -> Result<AuthBody, AuthError> {
-    use jsonwebtoken::{Algorithm, Header, encode};
-    
+impl Claims {
+    /// Who made this request, for recording with their changes.
+    pub fn author(&self) -> &str {
+        &self.sub
+    }
+}
+
+pub fn make_jwt_token(
+    appstate: &AppState,
+    registration: &Registration,
+) -> Result<AuthBody, AuthError> {
+    use jsonwebtoken::{encode, Algorithm, Header};
+
     if registration.password != appstate.reg_key {
         return Err(AuthError::Registration);
     }
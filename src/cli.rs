@@ -0,0 +1,203 @@
+use crate::*;
+
+use std::path::{Path as FsPath, PathBuf};
+
+/// Revision author for changes made from the command line.
+const CLI_AUTHOR: &str = "cli";
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Import jokes from the `*.json` files in a directory.
+    Import {
+        /// What to do with jokes whose id is already in use.
+        #[clap(long, value_enum, default_value = "skip")]
+        policy: ConflictPolicy,
+        /// Commit after this many jokes rather than all at once.
+        #[clap(long)]
+        batch_size: Option<usize>,
+        /// Directory of joke files.
+        #[clap(default_value = "jokes")]
+        dir: PathBuf,
+    },
+    /// Import jokes from numbered plain text, like
+    /// `joke-working/jokes-la-jolla.txt`, or from a fortune file.
+    ImportText {
+        /// Layout of the text.
+        #[clap(long, value_enum, default_value = "numbered")]
+        format: TextFormat,
+        /// Source to give the jokes that don't say.
+        #[clap(long)]
+        source: Option<String>,
+        /// Only show what would be imported.
+        #[clap(long)]
+        dry_run: bool,
+        /// What to do with jokes whose id is already in use.
+        #[clap(long, value_enum, default_value = "skip")]
+        policy: ConflictPolicy,
+        /// Commit after this many jokes rather than all at once.
+        #[clap(long)]
+        batch_size: Option<usize>,
+        /// Text file of jokes.
+        file: PathBuf,
+    },
+    /// Write out the whole jokebase.
+    Export {
+        /// Layout of the dump.
+        #[clap(long, value_enum, default_value = "json")]
+        format: DumpFormat,
+        /// File to write instead of standard output.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Write out jokes as a fortune(6) file.
+    Fortune {
+        /// Only write jokes with this tag.
+        #[clap(long)]
+        tag: Option<String>,
+        /// File to write instead of standard output.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// List groups of jokes that look like copies of each other.
+    Duplicates,
+    /// Check every joke against the wordlist again, and list
+    /// those flagged for review.
+    Flag,
+    /// Replace the whole jokebase with a dump made by `export`.
+    Restore {
+        /// Dump file, in any export format.
+        file: PathBuf,
+    },
+}
+
+pub async fn run_command(
+    command: Command,
+    duplicates: DuplicatePolicy,
+) -> Result<(), Box<dyn Error>> {
+    let mut jokebase = JokeBase::new().await?;
+    match command {
+        Command::Import {
+            policy,
+            batch_size,
+            dir,
+        } => {
+            let jokes = read_joke_dir(&dir).await?;
+            let results = jokebase
+                .import(&jokes, policy, duplicates, batch_size, CLI_AUTHOR)
+                .await?;
+            report_import(&results)
+        }
+        Command::ImportText {
+            format,
+            source,
+            dry_run,
+            policy,
+            batch_size,
+            file,
+        } => {
+            let text = tokio::fs::read_to_string(&file).await?;
+            let parsed = parse_text(&text, format, source.as_deref());
+            let report = jokebase
+                .import_text(parsed, dry_run, policy, duplicates, batch_size, CLI_AUTHOR)
+                .await?;
+            for warning in &report.parsed.warnings {
+                eprintln!("{}: {}", file.display(), warning);
+            }
+            for id in &report.collisions {
+                eprintln!("{}: id already in use", id);
+            }
+            match report.results {
+                Some(results) => report_import(&results),
+                None => {
+                    for joke in &report.parsed.jokes {
+                        println!("{}", serde_json::to_string(joke)?);
+                    }
+                    Ok(())
+                }
+            }
+        }
+        Command::Export { format, output } => {
+            let dump = format_dump(&jokebase.export().await?, format);
+            match output {
+                Some(path) => tokio::fs::write(path, dump).await?,
+                None => print!("{}", dump),
+            }
+            Ok(())
+        }
+        Command::Fortune { tag, output } => {
+            let jokes = jokebase.get_jokes(false).await?;
+            let fortunes = format_fortunes(&jokes, tag.as_deref());
+            match output {
+                Some(path) => tokio::fs::write(path, fortunes).await?,
+                None => print!("{}", fortunes),
+            }
+            Ok(())
+        }
+        Command::Duplicates => {
+            for cluster in jokebase.find_duplicates().await? {
+                println!("{}: {}", cluster.whos_there, cluster.ids.join(" "));
+            }
+            Ok(())
+        }
+        Command::Flag => {
+            for joke in jokebase.flag_all().await? {
+                println!(
+                    "{} ({}): {}",
+                    joke.id,
+                    joke.audience.as_str(),
+                    joke.flagged.join(" ")
+                );
+            }
+            Ok(())
+        }
+        Command::Restore { file } => {
+            let records = parse_dump(&tokio::fs::read_to_string(file).await?)?;
+            jokebase.load_dump(&records, CLI_AUTHOR).await?;
+            println!("restored {} jokes", records.len());
+            Ok(())
+        }
+    }
+}
+
+async fn read_joke_dir(dir: &FsPath) -> Result<Vec<Joke>, Box<dyn Error>> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut jokes = Vec::new();
+    for path in paths {
+        let text = tokio::fs::read_to_string(&path).await?;
+        let file_jokes = parse_jokes(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        jokes.extend(file_jokes);
+    }
+    Ok(jokes)
+}
+
+fn report_import(results: &[ImportResult]) -> Result<(), Box<dyn Error>> {
+    let mut failures = 0;
+    for result in results {
+        match &result.error {
+            Some(error) => println!("{}: {}: {}", result.id, result.status.as_str(), error),
+            None => println!("{}: {}", result.id, result.status.as_str()),
+        }
+        if !result.similar_to.is_empty() {
+            println!("{}: looks like {}", result.id, result.similar_to.join(", "));
+        }
+        if matches!(
+            result.status,
+            ImportStatus::Failed | ImportStatus::RolledBack
+        ) {
+            failures += 1;
+        }
+    }
+    if failures > 0 {
+        return Err(format!("{} of {} jokes not imported", failures, results.len()).into());
+    }
+    Ok(())
+}
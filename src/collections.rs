@@ -0,0 +1,77 @@
+//! Collections: named, ordered lists of jokes for telling in
+//! sequence, such as a Halloween set or a toddler-safe list.
+
+use crate::*;
+
+pub const MAX_COLLECTION_NAME_LEN: usize = 100;
+pub const MAX_COLLECTION_DESCRIPTION_LEN: usize = 500;
+pub const MAX_COLLECTION_JOKES: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Collection {
+    #[schema(example = "halloween")]
+    pub id: String,
+    #[schema(example = "Halloween set")]
+    pub name: String,
+    #[schema(example = "Spooky jokes for the last week of October")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Ids of the jokes, in telling order.
+    #[schema(example = json!(["boo", "witch", "ghost"]))]
+    #[serde(default)]
+    pub jokes: Vec<String>,
+}
+
+/// How to go through a collection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CollectionOrder {
+    /// One joke after another, starting over at the end.
+    #[default]
+    Sequential,
+    /// Any joke of the collection.
+    Random,
+}
+
+/// Tidy a collection and check it, giving back the collection
+/// as it should be stored or the problems with each field.
+pub fn validate_collection(collection: Collection) -> Result<Collection, JokeBaseErr> {
+    let collection = Collection {
+        id: collection.id.trim().to_string(),
+        name: collection.name.trim().to_string(),
+        description: collection
+            .description
+            .map(|description| description.trim().to_string())
+            .filter(|description| !description.is_empty()),
+        jokes: collection
+            .jokes
+            .iter()
+            .map(|id| id.trim().to_string())
+            .collect(),
+    };
+
+    let mut errors: Vec<FieldError> = [
+        check_id(&collection.id),
+        check_text("name", &collection.name, MAX_COLLECTION_NAME_LEN),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if let Some(description) = &collection.description {
+        errors.extend(check_text(
+            "description",
+            description,
+            MAX_COLLECTION_DESCRIPTION_LEN,
+        ));
+    }
+    if collection.jokes.len() > MAX_COLLECTION_JOKES {
+        let message = format!("at most {} jokes are allowed", MAX_COLLECTION_JOKES);
+        errors.push(FieldError::new("jokes", message));
+    }
+
+    if errors.is_empty() {
+        Ok(collection)
+    } else {
+        Err(JokeBaseErr::CollectionInvalid(errors))
+    }
+}
@@ -0,0 +1,160 @@
+use crate::*;
+
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DumpFormat {
+    /// JSON array of jokes.
+    #[default]
+    Json,
+    /// One JSON joke per line.
+    Ndjson,
+    /// JSON object mapping joke ids to jokes.
+    Map,
+}
+
+impl DumpFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            DumpFormat::Json | DumpFormat::Map => "application/json",
+            DumpFormat::Ndjson => NDJSON_CONTENT_TYPE,
+        }
+    }
+}
+
+/// A joke together with the jokebase's bookkeeping about it.
+/// Views and collections are not part of it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JokeRecord {
+    #[serde(flatten)]
+    pub joke: Joke,
+    /// Words flagged for review, kept as reviewers left them.
+    /// Jokes from dumps without this are checked again.
+    #[schema(example = json!([]))]
+    #[serde(default)]
+    pub flagged: Option<Vec<String>>,
+    /// When the joke was added.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
+    /// When the joke was last changed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<DateTime<Utc>>,
+    /// When the joke was moved to the trash, if it is there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<DateTime<Utc>>,
+    /// Old ids that redirect to this joke.
+    #[schema(example = r#"["europe-not"]"#)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Every vote cast for the joke.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ratings: Vec<VoteRecord>,
+}
+
+/// One voter's rating of a joke.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct VoteRecord {
+    #[schema(example = "user:bart")]
+    pub voter: String,
+    #[schema(example = 4, minimum = 1, maximum = 5)]
+    pub rating: i16,
+    pub created: DateTime<Utc>,
+}
+
+impl JokeRecord {
+    /// Tidy and check the record, naming any problems by its
+    /// position in the dump.
+    pub fn validate(self, index: usize) -> Result<JokeRecord, Vec<FieldError>> {
+        let field = |error: FieldError| {
+            let field = format!("[{}].{}", index, error.field);
+            error.for_field(&field)
+        };
+        let mut errors: Vec<FieldError> = Vec::new();
+        let joke = match validate_joke(self.joke) {
+            Ok(joke) => Some(joke),
+            Err(JokeBaseErr::JokeInvalid(joke_errors)) => {
+                errors.extend(joke_errors.into_iter().map(field));
+                None
+            }
+            Err(e) => {
+                errors.push(field(FieldError::new("joke", e.to_string())));
+                None
+            }
+        };
+        for alias in &self.aliases {
+            if let Some(error) = check_id(alias) {
+                errors.push(field(error.for_field("aliases")));
+            }
+        }
+        let mut voters: HashSet<&str> = HashSet::with_capacity(self.ratings.len());
+        for vote in &self.ratings {
+            if let Err(JokeBaseErr::JokeInvalid(rating_errors)) = check_rating(vote.rating) {
+                errors.extend(
+                    rating_errors
+                        .into_iter()
+                        .map(|error| field(error.for_field("ratings"))),
+                );
+            }
+            if !voters.insert(&vote.voter) {
+                let message = format!("{} voted twice", vote.voter);
+                errors.push(field(FieldError::new("ratings", message)));
+            }
+        }
+        match joke {
+            Some(joke) if errors.is_empty() => Ok(JokeRecord { joke, ..self }),
+            _ => Err(errors),
+        }
+    }
+}
+
+pub fn format_dump(records: &[JokeRecord], format: DumpFormat) -> String {
+    match format {
+        DumpFormat::Json => serde_json::to_string_pretty(records).unwrap(),
+        DumpFormat::Ndjson => records
+            .iter()
+            .map(|r| serde_json::to_string(r).unwrap() + "\n")
+            .collect(),
+        DumpFormat::Map => {
+            let map: BTreeMap<&str, &JokeRecord> =
+                records.iter().map(|r| (r.joke.id.as_str(), r)).collect();
+            serde_json::to_string_pretty(&map).unwrap()
+        }
+    }
+}
+
+/// Check every record of a dump, giving back the tidied
+/// records or the problems with all of them.
+pub fn validate_dump(records: Vec<JokeRecord>) -> Result<Vec<JokeRecord>, JokeBaseErr> {
+    let mut ids: HashSet<String> = HashSet::with_capacity(records.len());
+    let mut valid: Vec<JokeRecord> = Vec::with_capacity(records.len());
+    let mut errors: Vec<FieldError> = Vec::new();
+    for (index, record) in records.into_iter().enumerate() {
+        match record.validate(index) {
+            Ok(record) => {
+                let taken = [&record.joke.id].into_iter().chain(&record.aliases);
+                for id in taken {
+                    if !ids.insert(id.clone()) {
+                        let field = format!("[{}].id", index);
+                        errors.push(FieldError::new(&field, format!("{} is used twice", id)));
+                    }
+                }
+                valid.push(record);
+            }
+            Err(record_errors) => errors.extend(record_errors),
+        }
+    }
+    if errors.is_empty() {
+        Ok(valid)
+    } else {
+        Err(JokeBaseErr::DumpInvalid(errors))
+    }
+}
+
+/// Read a dump in any of the `DumpFormat`s.
+pub fn parse_dump(text: &str) -> Result<Vec<JokeRecord>, JokeBaseErr> {
+    if let Ok(map) = serde_json::from_str::<BTreeMap<String, JokeRecord>>(text) {
+        return Ok(map.into_values().collect());
+    }
+    parse_json_items(text)
+}
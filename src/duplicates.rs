@@ -0,0 +1,128 @@
+//! Spotting jokes that are copies of each other with small
+//! differences, as happens when the same joke is collected
+//! from several sites.
+
+use crate::*;
+
+/// What to do when a new joke looks like one already in the
+/// jokebase.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// Don't check.
+    Off,
+    /// Add the joke, but point out the similar ones.
+    #[default]
+    Warn,
+    /// Refuse the joke.
+    Reject,
+}
+
+/// Answers at least this similar, after normalizing, are taken
+/// to be the same.
+pub const SIMILARITY_THRESHOLD: f64 = 0.8;
+
+/// Text with case, punctuation (including straight and curly
+/// quotes) and extra whitespace removed.
+pub fn normalize_text(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Similarity of two normalized texts, from 0 (nothing alike)
+/// to 1 (the same).
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(&a, &b) as f64 / longest as f64
+}
+
+/// True if the jokes have the same "who's there" and similar
+/// answers.
+pub fn is_near_duplicate(a: &Joke, b: &Joke) -> bool {
+    normalize_text(&a.whos_there) == normalize_text(&b.whos_there)
+        && similarity(
+            &normalize_text(&a.answer_who),
+            &normalize_text(&b.answer_who),
+        ) >= SIMILARITY_THRESHOLD
+}
+
+/// A group of jokes that look like copies of each other.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DuplicateCluster {
+    #[schema(example = "Boo")]
+    pub whos_there: String,
+    #[schema(example = json!(["boo", "boo-cry"]))]
+    pub ids: Vec<String>,
+}
+
+/// Clusters of near-duplicate jokes, each joke being a near
+/// duplicate of at least one other in its cluster.
+pub fn duplicate_clusters(jokes: &[Joke]) -> Vec<DuplicateCluster> {
+    let mut groups: BTreeMap<String, Vec<&Joke>> = BTreeMap::new();
+    for joke in jokes {
+        groups
+            .entry(normalize_text(&joke.whos_there))
+            .or_default()
+            .push(joke);
+    }
+
+    let mut clusters = Vec::new();
+    for group in groups.values().filter(|group| group.len() > 1) {
+        // Union-find over the jokes in the group.
+        let mut parent: Vec<usize> = (0..group.len()).collect();
+        fn root(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+        for i in 0..group.len() {
+            for j in i + 1..group.len() {
+                if is_near_duplicate(group[i], group[j]) {
+                    let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[ri] = rj;
+                }
+            }
+        }
+
+        let mut members: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for (i, joke) in group.iter().enumerate() {
+            members
+                .entry(root(&mut parent, i))
+                .or_default()
+                .push(joke.id.clone());
+        }
+        for mut ids in members.into_values().filter(|ids| ids.len() > 1) {
+            ids.sort();
+            clusters.push(DuplicateCluster {
+                whos_there: group[0].whos_there.clone(),
+                ids,
+            });
+        }
+    }
+    clusters
+}
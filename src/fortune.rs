@@ -0,0 +1,128 @@
+//! Jokes in the text format read by `fortune(6)`: entries
+//! separated by lines holding just `%`, each entry being a
+//! joke as rendered by `impl From<&Joke> for String`.
+
+use crate::*;
+
+/// Fortune file of the given jokes, in id order, keeping only
+/// those with `tag` if it is given.
+pub fn format_fortunes(jokes: &[Joke], tag: Option<&str>) -> String {
+    let mut jokes: Vec<&Joke> = jokes
+        .iter()
+        .filter(|joke| match (tag, &joke.tags) {
+            (None, _) => true,
+            (Some(tag), Some(tags)) => tags.contains(tag),
+            (Some(_), None) => false,
+        })
+        .collect();
+    jokes.sort_by(|a, b| a.id.cmp(&b.id));
+    jokes
+        .into_iter()
+        .map(|joke| String::from(joke) + "%\n")
+        .collect()
+}
+
+/// Fill in id, tags, language, audience and source from an annotation
+/// line like `[id: boo; tags: kids, food; lang: en; audience: teen;
+/// source: "http://example.com"]`.
+fn parse_annotation(line: &str, joke: &mut Joke) {
+    let Some(inner) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) else {
+        return;
+    };
+    // The source is last, and may itself contain `;`.
+    let (fields, source) = match inner.find(r#"source: ""#) {
+        Some(i) => (&inner[..i], Some(inner[i + 9..].trim_end_matches('"'))),
+        None => (inner, None),
+    };
+    for field in fields.split(';').map(str::trim) {
+        if let Some(id) = field.strip_prefix("id: ") {
+            joke.id = id.trim().to_string();
+        } else if let Some(tags) = field.strip_prefix("tags: ") {
+            let tags: HashSet<String> = tags
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect();
+            joke.tags = if tags.is_empty() { None } else { Some(tags) };
+        } else if let Some(lang) = field.strip_prefix("lang: ") {
+            joke.lang = lang.trim().to_string();
+        } else if let Some(audience) = field.strip_prefix("audience: ") {
+            joke.audience = audience.trim().parse().unwrap_or_default();
+        }
+    }
+    if let Some(source) = source {
+        joke.source = Some(source.to_string());
+    }
+}
+
+/// Whether told lines are the classic five, allowing for an
+/// answer wrapped over several lines.
+fn is_classic(who: &str, lines: &[&str]) -> bool {
+    is_who_who(lines[3], who)
+        && !lines[4..].iter().any(|line| {
+            is_knock_knock(line)
+                || line.ends_with('—')
+                || (line.starts_with('(') && line.ends_with(')'))
+        })
+}
+
+fn parse_fortune(entry: &str, source: Option<&str>) -> Option<Joke> {
+    let mut lines: Vec<&str> = entry
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    let annotation = match lines.last() {
+        Some(line) if line.starts_with('[') => lines.pop(),
+        _ => None,
+    };
+    if lines.len() < 5 || !is_knock_knock(lines[0]) || !is_whos_there(lines[1]) {
+        return None;
+    }
+    let who = lines[2].trim_end_matches(['.', '!']);
+    let mut joke = if is_classic(who, &lines) {
+        Joke::new(&slugify(who), who, &lines[4..].join(" "), &[], source)
+    } else {
+        let turns = parse_turns(&lines);
+        let answer = turns
+            .iter()
+            .rev()
+            .find(|turn| turn.speaker != Speaker::Direction);
+        let answer = answer.map(|turn| turn.line.clone()).unwrap_or_default();
+        let mut joke = Joke::new(&slugify(who), who, &answer, &[], source);
+        joke.script = Some(turns);
+        joke
+    };
+    if let Some(annotation) = annotation {
+        parse_annotation(annotation, &mut joke);
+    }
+    Some(joke)
+}
+
+/// Read the knock-knock jokes in a fortune file. Jokes without
+/// an annotation get an id made from who is there, and the
+/// given source.
+pub fn parse_fortunes(text: &str, source: Option<&str>) -> TextJokes {
+    let mut parsed = TextJokes::default();
+    let mut entry = String::new();
+    let mut count = 0;
+    for line in text.lines().chain(std::iter::once("%")) {
+        if line.trim_end() != "%" {
+            entry += line;
+            entry += "\n";
+            continue;
+        }
+        if !entry.trim().is_empty() {
+            count += 1;
+            match parse_fortune(&entry, source) {
+                Some(joke) => parsed.jokes.push(joke),
+                None => parsed
+                    .warnings
+                    .push(format!("fortune {}: not a knock-knock joke", count)),
+            }
+        }
+        entry.clear();
+    }
+    parsed
+}
@@ -0,0 +1,114 @@
+use crate::*;
+
+/// What to do with an imported joke whose id is already in use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Keep the existing joke.
+    #[default]
+    Skip,
+    /// Replace the existing joke.
+    Overwrite,
+    /// Fail, rolling back the batch. This goes for any joke
+    /// that can't be imported, not just one whose id is in use.
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Added,
+    Overwritten,
+    Skipped,
+    /// Not imported because it looks like a copy of a joke
+    /// already there.
+    Duplicate,
+    Failed,
+    /// Not imported because another joke in its batch failed
+    /// under `ConflictPolicy::Fail`.
+    #[serde(rename = "rolled-back")]
+    RolledBack,
+}
+
+impl ImportStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImportStatus::Added => "added",
+            ImportStatus::Overwritten => "overwritten",
+            ImportStatus::Skipped => "skipped",
+            ImportStatus::Duplicate => "duplicate",
+            ImportStatus::Failed => "failed",
+            ImportStatus::RolledBack => "rolled-back",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ImportResult {
+    #[schema(example = "boo")]
+    pub id: String,
+    pub status: ImportStatus,
+    #[schema(example = "joke already exists: boo")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Jokes already there that this one looks like a copy of.
+    #[schema(example = json!(["boo-cry"]))]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub similar_to: Vec<String>,
+}
+
+impl ImportResult {
+    pub fn new(id: &str, status: ImportStatus) -> Self {
+        Self {
+            id: id.to_string(),
+            status,
+            error: None,
+            similar_to: Vec::new(),
+        }
+    }
+
+    pub fn similar_to(self, ids: Vec<String>) -> Self {
+        Self {
+            similar_to: ids,
+            ..self
+        }
+    }
+
+    pub fn failed(id: &str, error: JokeBaseErr) -> Self {
+        Self {
+            id: id.to_string(),
+            status: ImportStatus::Failed,
+            error: Some(error.to_string()),
+            similar_to: Vec::new(),
+        }
+    }
+}
+
+/// Read jokes given as a JSON array, a single JSON joke, or
+/// newline-delimited JSON with one joke per line.
+pub fn parse_jokes(text: &str) -> Result<Vec<Joke>, JokeBaseErr> {
+    parse_json_items(text)
+}
+
+/// Read items given as a JSON array, a single JSON object, or
+/// newline-delimited JSON with one item per line.
+pub fn parse_json_items<T: DeserializeOwned>(text: &str) -> Result<Vec<T>, JokeBaseErr> {
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('[') {
+        return serde_json::from_str(text)
+            .map_err(|e| JokeBaseErr::JokeUnprocessable(e.to_string()));
+    }
+    if let Ok(item) = serde_json::from_str::<T>(text) {
+        return Ok(vec![item]);
+    }
+    let mut items = Vec::new();
+    for (lineno, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let item = serde_json::from_str(line)
+            .map_err(|e| JokeBaseErr::JokeUnprocessable(format!("line {}: {}", lineno + 1, e)))?;
+        items.push(item);
+    }
+    Ok(items)
+}
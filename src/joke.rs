@@ -14,6 +14,32 @@ pub struct Joke {
     #[schema(example = "http://example.com/knock-knock-jokes")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
+    /// How the joke is told, for jokes that don't fit the
+    /// classic five lines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<Vec<Turn>>,
+    /// Language the joke is written in.
+    #[schema(example = "en")]
+    #[serde(default = "default_lang")]
+    pub lang: String,
+    /// Id of the joke this one translates.
+    #[schema(example = "boo")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation_of: Option<String>,
+    /// Who the joke is fit for.
+    #[serde(default)]
+    pub audience: Audience,
+    /// Words the wordlist found beyond the joke's audience, until
+    /// a reviewer has looked at it. Set by the jokebase, so
+    /// ignored on input.
+    #[schema(read_only, example = json!([]))]
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub flagged: Vec<String>,
+    /// Visitors' ratings. Not part of the joke's content, so
+    /// ignored on input and left out of versions and diffs.
+    #[schema(read_only)]
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<Rating>,
 }
 
 impl Joke {
@@ -39,8 +65,181 @@ impl Joke {
             answer_who,
             tags,
             source,
+            script: None,
+            lang: default_lang(),
+            translation_of: None,
+            audience: Audience::default(),
+            flagged: Vec::new(),
+            rating: None,
         }
     }
+
+    /// Whether the joke can be told in family-safe mode.
+    pub fn is_family_safe(&self) -> bool {
+        self.audience == Audience::AllAges && self.flagged.is_empty()
+    }
+
+    /// Id of the original of the joke's translations.
+    pub fn original_id(&self) -> &str {
+        self.translation_of.as_deref().unwrap_or(&self.id)
+    }
+
+    /// The joke's script, or the classic five lines.
+    pub fn turns(&self) -> Vec<Turn> {
+        match &self.script {
+            Some(script) => script.clone(),
+            None => classic_turns(&self.lang, &self.whos_there, &self.answer_who),
+        }
+    }
+
+    /// "Boo who?", in the joke's language.
+    pub fn who_who(&self) -> String {
+        who_who_line(&self.lang, &self.whos_there)
+    }
+
+    /// Lines of the joke as they are told.
+    pub fn told(&self) -> Vec<Turn> {
+        told_turns(&self.turns())
+    }
+
+    /// JSON form of the joke's content with its tags sorted, so that two
+    /// jokes with the same tags compare equal.
+    pub fn canonical_json(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut joke = serde_json::to_value(self).unwrap();
+        let fields = joke.as_object_mut().unwrap();
+        fields.remove("rating");
+        fields.remove("flagged");
+        if let Some(serde_json::Value::Array(tags)) = joke.get_mut("tags") {
+            tags.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+        }
+        match joke {
+            serde_json::Value::Object(fields) => fields,
+            _ => unreachable!("joke is not a JSON object"),
+        }
+    }
+
+    /// Entity tag identifying this version of the joke.
+    pub fn etag(&self) -> ETag {
+        self.format_etag(JokeFormat::Json)
+    }
+
+    /// Entity tag identifying this version of the joke in the
+    /// given format: the hash of its content in that format,
+    /// then the hash of its rating and flagged words, which are
+    /// served with it but are not part of the content.
+    pub fn format_etag(&self, format: JokeFormat) -> ETag {
+        make_etag(&self.served_hash(format))
+    }
+
+    /// True if `tag`, as sent in `If-Match`, names this version of
+    /// the joke's content in any format. Only the content part is
+    /// compared, so a vote or a check of its words in between does
+    /// not count as a change.
+    pub fn content_matches(&self, tag: &str) -> bool {
+        let Some(opaque) = tag.strip_prefix('"').and_then(|tag| tag.strip_suffix('"')) else {
+            return false;
+        };
+        let content = opaque
+            .split_once('-')
+            .map_or(opaque, |(content, _)| content);
+        JokeFormat::ALL
+            .iter()
+            .any(|&format| self.format_content_hash(format) == content)
+    }
+
+    fn content_hash(&self) -> String {
+        let json = serde_json::Value::Object(self.canonical_json()).to_string();
+        hash_hex(&[json.as_bytes()])
+    }
+
+    /// Hash of the content in the given format. JSON, the form
+    /// jokes are written in, has the plain content hash.
+    fn format_content_hash(&self, format: JokeFormat) -> String {
+        let hash = self.content_hash();
+        match format {
+            JokeFormat::Json => hash,
+            format => hash_hex(&[hash.as_bytes(), format.content_type().as_bytes()]),
+        }
+    }
+
+    /// Hash of the joke as it is served in the given format.
+    fn served_hash(&self, format: JokeFormat) -> String {
+        let rating = serde_json::to_string(&self.rating).unwrap();
+        let flagged = serde_json::to_string(&self.flagged).unwrap();
+        let state = hash_hex(&[rating.as_bytes(), flagged.as_bytes()]);
+        format!("{}-{}", self.format_content_hash(format), state)
+    }
+}
+
+/// A joke as submitted for adding. Without an id, one is made
+/// up from `whos_there`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct NewJoke {
+    #[schema(example = "boo")]
+    pub id: Option<String>,
+    #[schema(example = "Boo")]
+    pub whos_there: String,
+    #[schema(example = "You don't have to cry about it!")]
+    pub answer_who: String,
+    #[schema(example = r#"["kids", "food"]"#)]
+    pub tags: Option<HashSet<String>>,
+    #[schema(example = "http://example.com/knock-knock-jokes")]
+    pub source: Option<String>,
+    pub script: Option<Vec<Turn>>,
+    #[schema(example = "en")]
+    pub lang: Option<String>,
+    #[schema(example = "boo")]
+    pub translation_of: Option<String>,
+    #[schema(example = "all-ages")]
+    pub audience: Option<Audience>,
+}
+
+impl From<NewJoke> for Joke {
+    fn from(joke: NewJoke) -> Self {
+        Joke {
+            id: joke.id.unwrap_or_default(),
+            whos_there: joke.whos_there,
+            answer_who: joke.answer_who,
+            tags: joke.tags,
+            source: joke.source,
+            script: joke.script,
+            lang: joke.lang.unwrap_or_else(default_lang),
+            translation_of: joke.translation_of,
+            audience: joke.audience.unwrap_or_default(),
+            flagged: Vec::new(),
+            rating: None,
+        }
+    }
+}
+
+/// Entity tag identifying this version of a list of jokes in
+/// the given format, in the order they are served.
+pub fn jokes_etag(jokes: &[Joke], format: JokeFormat) -> ETag {
+    let hashes: Vec<String> = jokes
+        .iter()
+        .map(|joke| joke.served_hash(JokeFormat::Json))
+        .collect();
+    let mut hashes: Vec<&[u8]> = hashes.iter().map(String::as_bytes).collect();
+    hashes.push(format.content_type().as_bytes());
+    make_etag(&hash_hex(&hashes))
+}
+
+fn hash_hex(parts: &[&[u8]]) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+        hasher.update(b"\n");
+    }
+    hasher.finalize()[..16]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn make_etag(hash: &str) -> ETag {
+    format!("\"{}\"", hash).parse().unwrap()
 }
 
 pub fn format_tags(tags: &HashSet<String>) -> String {
@@ -50,17 +249,22 @@ pub fn format_tags(tags: &HashSet<String>) -> String {
 
 impl From<&Joke> for String {
     fn from(joke: &Joke) -> Self {
-        let mut text: String = "Knock knock!\n".into();
-        text += "Who's there?\n";
-        text += &format!("{}.\n", joke.whos_there);
-        text += &format!("\"{}\" who?\n", joke.whos_there);
-        text += &format!("{}\n", joke.answer_who);
+        let mut text = String::new();
+        for turn in joke.told() {
+            text += &format!("{}\n", turn.line);
+        }
         text += "\n";
 
         let mut annote: Vec<String> = vec![format!("id: {}", joke.id)];
         if let Some(tags) = &joke.tags {
             annote.push(format!("tags: {}", format_tags(tags)));
         }
+        if joke.lang != DEFAULT_LANG {
+            annote.push(format!("lang: {}", joke.lang));
+        }
+        if joke.audience != Audience::AllAges {
+            annote.push(format!("audience: {}", joke.audience.as_str()));
+        }
         if let Some(source) = &joke.source {
             annote.push(format!(r#"source: "{}""#, source));
         }
@@ -75,3 +279,35 @@ impl IntoResponse for &Joke {
         (StatusCode::OK, Json(&self)).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(joke: &Joke, format: JokeFormat) -> String {
+        format!("\"{}\"", joke.served_hash(format))
+    }
+
+    #[test]
+    fn rating_changes_etag_but_not_content() {
+        let mut joke = Joke::new("boo", "Boo", "Don't cry!", &["kids"], None);
+        let json = tag(&joke, JokeFormat::Json);
+        let html = tag(&joke, JokeFormat::Html);
+        joke.rating = Rating::from_totals(1, 3);
+        assert_ne!(tag(&joke, JokeFormat::Json), json);
+        assert_ne!(tag(&joke, JokeFormat::Html), html);
+        assert!(joke.content_matches(&json));
+        assert!(joke.content_matches(&html));
+        joke.answer_who = "Don't cry about it!".to_string();
+        assert!(!joke.content_matches(&json));
+    }
+
+    #[test]
+    fn flagged_words_change_etag_but_not_content() {
+        let mut joke = Joke::new("boo", "Boo", "Don't cry!", &["kids"], None);
+        let json = tag(&joke, JokeFormat::Json);
+        joke.flagged = vec!["cry".to_string()];
+        assert_ne!(tag(&joke, JokeFormat::Json), json);
+        assert!(joke.content_matches(&json));
+    }
+}
@@ -14,8 +14,36 @@ pub enum JokeBaseErr {
     JokeDoesNotExist(String),
     #[error("joke payload unprocessable: {0}")]
     JokeUnprocessable(String),
+    #[error("joke is invalid: {}", format_field_errors(.0))]
+    JokeInvalid(Vec<FieldError>),
     #[error("database error: {0}")]
     DatabaseError(String),
+    #[error("database unavailable: {0}")]
+    DatabaseUnavailable(String),
+    #[error("joke has been renamed to {0}")]
+    JokeRenamed(String),
+    #[error("joke {0} has changed since it was read")]
+    JokeChanged(String),
+    #[error("joke {0} has no revision {1}")]
+    RevisionDoesNotExist(String, i32),
+    #[error("joke looks like a copy of joke {0}")]
+    DuplicateJoke(String),
+    #[error("collection already exists: {0}")]
+    CollectionExists(String),
+    #[error("collection {0} doesn't exist")]
+    CollectionDoesNotExist(String),
+    #[error("collection is invalid: {}", format_field_errors(.0))]
+    CollectionInvalid(Vec<FieldError>),
+    #[error("tag already in use: {0}")]
+    TagExists(String),
+    #[error("tag {0} doesn't exist")]
+    TagDoesNotExist(String),
+    #[error("tag is invalid: {}", format_field_errors(.0))]
+    TagInvalid(Vec<FieldError>),
+    #[error("request parameter is invalid: {}", format_field_errors(.0))]
+    ParamInvalid(Vec<FieldError>),
+    #[error("dump is invalid: {}", format_field_errors(.0))]
+    DumpInvalid(Vec<FieldError>),
 }
 
 impl From<std::io::Error> for JokeBaseErr {
@@ -26,54 +54,82 @@ impl From<std::io::Error> for JokeBaseErr {
 
 impl From<sqlx::Error> for JokeBaseErr {
     fn from(e: sqlx::Error) -> Self {
-        JokeBaseErr::DatabaseError(e.to_string())
+        match e {
+            sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed => {
+                JokeBaseErr::DatabaseUnavailable(e.to_string())
+            }
+            e => JokeBaseErr::DatabaseError(e.to_string()),
+        }
     }
 }
 
-#[derive(Debug)]
-pub struct JokeBaseError {
-    pub status: StatusCode,
-    pub error: JokeBaseErr,
-}
-
-pub fn error_schema(name: &str, example: serde_json::Value) -> (&str, RefOr<Schema>) {
-    let sch = ObjectBuilder::new()
-        .property(
-            "status",
-            ObjectBuilder::new().schema_type(SchemaType::String),
-        )
-        .property(
-            "error",
-            ObjectBuilder::new().schema_type(SchemaType::String),
-        )
-        .example(Some(example))
-        .into();
-    (name, sch)
-}
-
-impl<'s> ToSchema<'s> for JokeBaseError {
-    fn schema() -> (&'s str, RefOr<Schema>) {
-        let example = serde_json::json!({
-            "status":"404","error":"no joke"
-        });
-        error_schema("JokeBaseError", example)
+impl JokeBaseErr {
+    /// HTTP status for reporting this error.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            JokeBaseErr::JokeExists(_) => StatusCode::CONFLICT,
+            JokeBaseErr::JokeBaseIoError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            JokeBaseErr::NoJoke => StatusCode::NOT_FOUND,
+            JokeBaseErr::JokeDoesNotExist(_) => StatusCode::NOT_FOUND,
+            JokeBaseErr::JokeUnprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            JokeBaseErr::JokeInvalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            JokeBaseErr::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            JokeBaseErr::DatabaseUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            JokeBaseErr::JokeRenamed(_) => StatusCode::PERMANENT_REDIRECT,
+            JokeBaseErr::JokeChanged(_) => StatusCode::PRECONDITION_FAILED,
+            JokeBaseErr::RevisionDoesNotExist(..) => StatusCode::NOT_FOUND,
+            JokeBaseErr::DuplicateJoke(_) => StatusCode::CONFLICT,
+            JokeBaseErr::CollectionExists(_) => StatusCode::CONFLICT,
+            JokeBaseErr::CollectionDoesNotExist(_) => StatusCode::NOT_FOUND,
+            JokeBaseErr::CollectionInvalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            JokeBaseErr::TagExists(_) => StatusCode::CONFLICT,
+            JokeBaseErr::TagDoesNotExist(_) => StatusCode::NOT_FOUND,
+            JokeBaseErr::TagInvalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            JokeBaseErr::ParamInvalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            JokeBaseErr::DumpInvalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
     }
-}
 
-impl Serialize for JokeBaseError {
-    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let status: String = self.status.to_string();
-        let mut state = serializer.serialize_struct("JokeBaseError", 2)?;
-        state.serialize_field("status", &status)?;
-        state.serialize_field("error", &self.error)?;
-        state.end()
+    /// Short name and title of the problem type for this error.
+    pub fn problem_type(&self) -> (&'static str, &'static str) {
+        match self {
+            JokeBaseErr::JokeExists(_) => ("joke-exists", "Joke already exists"),
+            JokeBaseErr::JokeBaseIoError(_) => ("jokebase-io-error", "Jokebase I/O failed"),
+            JokeBaseErr::NoJoke => ("no-joke", "Jokebase is empty"),
+            JokeBaseErr::JokeDoesNotExist(_) => ("joke-does-not-exist", "Joke does not exist"),
+            JokeBaseErr::JokeUnprocessable(_) => ("joke-unprocessable", "Joke is unprocessable"),
+            JokeBaseErr::JokeInvalid(_) => ("joke-invalid", "Joke is invalid"),
+            JokeBaseErr::DatabaseError(_) => ("database-error", "Database error"),
+            JokeBaseErr::DatabaseUnavailable(_) => ("database-unavailable", "Database unavailable"),
+            JokeBaseErr::JokeRenamed(_) => ("joke-renamed", "Joke has been renamed"),
+            JokeBaseErr::JokeChanged(_) => ("joke-changed", "Joke has changed"),
+            JokeBaseErr::RevisionDoesNotExist(..) => {
+                ("revision-does-not-exist", "Revision does not exist")
+            }
+            JokeBaseErr::DuplicateJoke(_) => ("duplicate-joke", "Joke is a near-duplicate"),
+            JokeBaseErr::CollectionExists(_) => ("collection-exists", "Collection already exists"),
+            JokeBaseErr::CollectionDoesNotExist(_) => {
+                ("collection-does-not-exist", "Collection does not exist")
+            }
+            JokeBaseErr::CollectionInvalid(_) => ("collection-invalid", "Collection is invalid"),
+            JokeBaseErr::TagExists(_) => ("tag-exists", "Tag already in use"),
+            JokeBaseErr::TagDoesNotExist(_) => ("tag-does-not-exist", "Tag does not exist"),
+            JokeBaseErr::TagInvalid(_) => ("tag-invalid", "Tag is invalid"),
+            JokeBaseErr::ParamInvalid(_) => ("param-invalid", "Request parameter is invalid"),
+            JokeBaseErr::DumpInvalid(_) => ("dump-invalid", "Dump is invalid"),
+        }
     }
-}
 
-impl JokeBaseError {
-    pub fn response(status: StatusCode, error: JokeBaseErr) -> Response {
-        let error = JokeBaseError { status, error };
-        (status, Json(error)).into_response()
+    /// The joke this error is about, if it names one.
+    pub fn joke_id(&self) -> Option<&str> {
+        match self {
+            JokeBaseErr::JokeExists(id)
+            | JokeBaseErr::JokeDoesNotExist(id)
+            | JokeBaseErr::JokeChanged(id)
+            | JokeBaseErr::RevisionDoesNotExist(id, _)
+            | JokeBaseErr::DuplicateJoke(id) => Some(id),
+            _ => None,
+        }
     }
 }
 
@@ -81,40 +137,241 @@ impl JokeBaseError {
 pub struct JokeBase(pub Pool<Postgres>);
 
 impl JokeBase {
-    async fn to_joke(&self, row: &PgRow) -> Result<Joke, sqlx::Error> {
+    async fn to_joke(conn: &mut PgConnection, row: &PgRow) -> Result<Joke, sqlx::Error> {
         let id = row.get("id");
         let tags = sqlx::query(r#"SELECT tag FROM tags WHERE id = $1"#)
             .bind(&id)
-            .fetch_all(&self.0)
+            .fetch_all(&mut *conn)
             .await?;
         let tags: HashSet<String> = tags.iter().map(|row| row.get("tag")).collect();
         let tags = if tags.is_empty() { None } else { Some(tags) };
+        let script: Option<sqlx::types::Json<Vec<Turn>>> = row.get("script");
+        let audience: &str = row.get("audience");
         Ok(Joke {
             id,
             whos_there: row.get("whos_there"),
             answer_who: row.get("answer_who"),
             source: row.get("source"),
             tags,
+            script: script.map(|script| script.0),
+            lang: row.get("lang"),
+            translation_of: row.get("translation_of"),
+            audience: audience.parse().unwrap_or_default(),
+            flagged: row.get("flagged"),
+            rating: Rating::from_totals(row.get("votes"), row.get("groans")),
         })
     }
 
+    async fn fetch_joke(conn: &mut PgConnection, index: &str) -> Result<Option<Joke>, sqlx::Error> {
+        let row = sqlx::query(r#"SELECT * FROM jokes WHERE id = $1 AND deleted IS NULL;"#)
+            .bind(index)
+            .fetch_optional(&mut *conn)
+            .await?;
+        match row {
+            Some(row) => Ok(Some(Self::to_joke(conn, &row).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn record_revision(
+        tx: &mut PgConnection,
+        id: &str,
+        action: RevisionAction,
+        author: &str,
+        old: Option<&Joke>,
+        new: Option<&Joke>,
+    ) -> Result<(), sqlx::Error> {
+        let joke = new.or(old).map(sqlx::types::Json);
+        sqlx::query(
+            r#"INSERT INTO revisions
+            (id, action, author, joke, diff)
+            VALUES ($1, $2, $3, $4, $5);"#,
+        )
+        .bind(id)
+        .bind(action.as_str())
+        .bind(author)
+        .bind(joke)
+        .bind(joke_diff(old, new))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(r#"UPDATE jokes SET updated = now() WHERE id = $1;"#)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        Ok(())
+    }
+
     async fn insert_tags(
         tx: &mut PgConnection,
         id: &str,
         tags: &Option<HashSet<String>>,
     ) -> Result<(), sqlx::Error> {
         if let Some(tags) = tags {
-            for tag in tags {
-                sqlx::query(r#"INSERT INTO tags (id, tag) VALUES ($1, $2);"#)
-                    .bind(id)
-                    .bind(tag)
-                    .execute(&mut *tx)
-                    .await?;
-            }
+            let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+            sqlx::query(
+                r#"INSERT INTO tags (id, tag)
+                SELECT $1, UNNEST($2::text[])
+                ON CONFLICT DO NOTHING;"#,
+            )
+            .bind(id)
+            .bind(tags)
+            .execute(&mut *tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Give the joke exactly these tags, leaving ones it keeps
+    /// alone.
+    async fn replace_tags(
+        tx: &mut PgConnection,
+        id: &str,
+        tags: &Option<HashSet<String>>,
+    ) -> Result<(), sqlx::Error> {
+        let keep: Vec<&str> = tags.iter().flatten().map(String::as_str).collect();
+        sqlx::query(r#"DELETE FROM tags WHERE id = $1 AND tag <> ALL($2);"#)
+            .bind(id)
+            .bind(keep)
+            .execute(&mut *tx)
+            .await?;
+        Self::insert_tags(tx, id, tags).await
+    }
+
+    /// Check the joke's words against the wordlist again, as
+    /// after its tags change, keeping `joke.flagged` in step.
+    async fn reflag(conn: &mut PgConnection, joke: &mut Joke) -> Result<(), sqlx::Error> {
+        let words = flag_words(joke);
+        if words != joke.flagged {
+            sqlx::query(r#"UPDATE jokes SET flagged = $2 WHERE id = $1;"#)
+                .bind(&joke.id)
+                .bind(&words)
+                .execute(&mut *conn)
+                .await?;
+            joke.flagged = words;
+        }
+        Ok(())
+    }
+
+    /// Tags with any aliases replaced by the tags they stand for.
+    async fn resolve_tags(
+        conn: &mut PgConnection,
+        tags: Option<HashSet<String>>,
+    ) -> Result<Option<HashSet<String>>, sqlx::Error> {
+        let Some(tags) = tags else {
+            return Ok(None);
+        };
+        let names: Vec<String> = tags.iter().cloned().collect();
+        let rows = sqlx::query(r#"SELECT alias, tag FROM tag_aliases WHERE alias = ANY($1);"#)
+            .bind(names)
+            .fetch_all(&mut *conn)
+            .await?;
+        let aliases: HashMap<String, String> = rows
+            .iter()
+            .map(|row| (row.get("alias"), row.get("tag")))
+            .collect();
+        Ok(Some(
+            tags.into_iter()
+                .map(|tag| aliases.get(&tag).cloned().unwrap_or(tag))
+                .collect(),
+        ))
+    }
+
+    /// The original a joke translates: a live joke, and never
+    /// itself a translation, so a translation of a translation
+    /// is taken as one of its original.
+    async fn resolve_translation(
+        conn: &mut PgConnection,
+        joke: &Joke,
+    ) -> Result<Option<String>, JokeBaseErr> {
+        let Some(original) = &joke.translation_of else {
+            return Ok(None);
+        };
+        let invalid =
+            |key| JokeBaseErr::JokeInvalid(vec![FieldError::from_key("translation_of", key, &[])]);
+        let row = sqlx::query(
+            r#"SELECT id, translation_of FROM jokes WHERE id = $1 AND deleted IS NULL;"#,
+        )
+        .bind(original)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| invalid("field.not_a_joke"))?;
+        let original: String = row
+            .get::<Option<String>, _>("translation_of")
+            .unwrap_or_else(|| row.get("id"));
+        if original == joke.id {
+            return Err(invalid("field.self_translation"));
+        }
+        let translated = sqlx::query(r#"SELECT 1 FROM jokes WHERE translation_of = $1 LIMIT 1;"#)
+            .bind(&joke.id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        if translated.is_some() {
+            return Err(invalid("field.has_translations"));
+        }
+        Ok(Some(original))
+    }
+
+    async fn insert_joke(tx: &mut PgConnection, joke: &Joke) -> Result<(), JokeBaseErr> {
+        // An old id keeps redirecting to the joke renamed from it.
+        if Self::alias_owner(tx, &joke.id).await?.is_some() {
+            return Err(JokeBaseErr::JokeExists(joke.id.to_string()));
         }
+        let result = sqlx::query(
+            r#"INSERT INTO jokes
+            (id, whos_there, answer_who, source, script, lang, translation_of, audience, flagged)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9);"#,
+        )
+        .bind(&joke.id)
+        .bind(&joke.whos_there)
+        .bind(&joke.answer_who)
+        .bind(&joke.source)
+        .bind(joke.script.as_ref().map(sqlx::types::Json))
+        .bind(&joke.lang)
+        .bind(&joke.translation_of)
+        .bind(joke.audience.as_str())
+        .bind(flag_words(joke))
+        .execute(&mut *tx)
+        .await;
+        result.map_err(|e| {
+            if let sqlx::Error::Database(ref dbe) = e {
+                if let Some("23505") = dbe.code().as_deref() {
+                    return JokeBaseErr::JokeExists(joke.id.to_string());
+                }
+            }
+            JokeBaseErr::DatabaseError(e.to_string())
+        })?;
+        Self::insert_tags(tx, &joke.id, &joke.tags).await?;
         Ok(())
     }
 
+    /// Write the joke whether or not its id is already in use,
+    /// taking it out of the trash if need be.
+    async fn upsert_joke(tx: &mut PgConnection, joke: &Joke) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"INSERT INTO jokes
+            (id, whos_there, answer_who, source, script, lang, translation_of, audience, flagged)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (id) DO UPDATE
+            SET (whos_there, answer_who, source, script, lang, translation_of, audience, flagged,
+                 deleted) =
+                (EXCLUDED.whos_there, EXCLUDED.answer_who, EXCLUDED.source, EXCLUDED.script,
+                 EXCLUDED.lang, EXCLUDED.translation_of, EXCLUDED.audience, EXCLUDED.flagged,
+                 NULL);"#,
+        )
+        .bind(&joke.id)
+        .bind(&joke.whos_there)
+        .bind(&joke.answer_who)
+        .bind(&joke.source)
+        .bind(joke.script.as_ref().map(sqlx::types::Json))
+        .bind(&joke.lang)
+        .bind(&joke.translation_of)
+        .bind(joke.audience.as_str())
+        .bind(flag_words(joke))
+        .execute(&mut *tx)
+        .await?;
+        Self::replace_tags(tx, &joke.id, &joke.tags).await
+    }
+
     pub async fn new() -> Result<Self, Box<dyn Error>> {
         use std::env::var;
 
@@ -131,99 +388,1627 @@ impl JokeBase {
         Ok(JokeBase(pool))
     }
 
-    pub async fn get_random(&self) -> Result<Joke, JokeBaseErr> {
-        let row = sqlx::query(r#"SELECT * FROM jokes ORDER BY RANDOM () LIMIT 1;"#)
-            .fetch_one(&self.0)
-            .await?;
+    /// Get a joke at random. If `weighted`, better rated jokes
+    /// are more likely to be chosen. Translations are left out:
+    /// `localize` the joke instead. If `family_safe`, only
+    /// all-ages jokes not waiting for review are chosen.
+    pub async fn get_random(&self, weighted: bool, family_safe: bool) -> Result<Joke, JokeBaseErr> {
+        let mut conn = self.0.acquire().await?;
+        let query = if weighted {
+            // Weighted sampling: the largest random()^(1 / weight)
+            // wins, compared as logarithms. Weights are ratings
+            // pulled toward PRIOR_RATING by PRIOR_VOTES.
+            sqlx::query(
+                r#"SELECT * FROM jokes WHERE deleted IS NULL AND translation_of IS NULL
+                AND (NOT $3 OR (audience = 'all-ages' AND cardinality(flagged) = 0))
+                ORDER BY ln(1.0 - random()) * (votes + $1) / (groans + $1 * $2) DESC
+                LIMIT 1;"#,
+            )
+            .bind(PRIOR_VOTES)
+            .bind(PRIOR_RATING)
+            .bind(family_safe)
+        } else {
+            sqlx::query(
+                r#"SELECT * FROM jokes WHERE deleted IS NULL AND translation_of IS NULL
+                AND (NOT $1 OR (audience = 'all-ages' AND cardinality(flagged) = 0))
+                ORDER BY RANDOM () LIMIT 1;"#,
+            )
+            .bind(family_safe)
+        };
+        let row = query
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or(JokeBaseErr::NoJoke)?;
 
-        let joke = self.to_joke(&row).await?;
+        let joke = Self::to_joke(&mut conn, &row).await?;
         Ok(joke)
     }
 
-    pub async fn get<'a>(&self, index: &str) -> Result<Joke, JokeBaseErr> {
-        let row = sqlx::query(r#"SELECT * FROM jokes WHERE id = $1;"#)
+    /// Get the joke with the given id. If the id is the old id
+    /// of a renamed joke, fails with `JokeRenamed` giving the
+    /// current id.
+    pub async fn get(&self, index: &str) -> Result<Joke, JokeBaseErr> {
+        let mut conn = self.0.acquire().await?;
+        let alias = sqlx::query(r#"SELECT id FROM aliases WHERE alias = $1;"#)
             .bind(index)
-            .fetch_one(&self.0)
+            .fetch_optional(&mut *conn)
             .await?;
+        if let Some(alias) = alias {
+            return Err(JokeBaseErr::JokeRenamed(alias.get("id")));
+        }
+        Self::fetch_joke(&mut conn, index)
+            .await?
+            .ok_or_else(|| JokeBaseErr::JokeDoesNotExist(index.to_string()))
+    }
+
+    /// A joke and its translations, the original first. If
+    /// `family_safe`, only those fit to tell in family-safe mode.
+    async fn fetch_translations(
+        conn: &mut PgConnection,
+        joke: &Joke,
+        family_safe: bool,
+    ) -> Result<Vec<Joke>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"SELECT * FROM jokes
+            WHERE (id = $1 OR translation_of = $1) AND deleted IS NULL
+            AND (NOT $2 OR (audience = 'all-ages' AND cardinality(flagged) = 0))
+            ORDER BY translation_of IS NOT NULL, lang, id;"#,
+        )
+        .bind(joke.original_id())
+        .bind(family_safe)
+        .fetch_all(&mut *conn)
+        .await?;
+        let mut jokes: Vec<Joke> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            jokes.push(Self::to_joke(conn, row).await?);
+        }
+        Ok(jokes)
+    }
 
-        let joke = self.to_joke(&row).await?;
+    /// The joke with the given id and its translations.
+    pub async fn translations(
+        &self,
+        index: &str,
+        family_safe: bool,
+    ) -> Result<Vec<Joke>, JokeBaseErr> {
+        let joke = self.get(index).await?;
+        let mut conn = self.0.acquire().await?;
+        Ok(Self::fetch_translations(&mut conn, &joke, family_safe).await?)
+    }
+
+    /// The joke in the first of `langs` it has been translated
+    /// into, or as it is if none.
+    pub async fn localize(
+        &self,
+        joke: Joke,
+        langs: &[String],
+        family_safe: bool,
+    ) -> Result<Joke, JokeBaseErr> {
+        if langs.first().map_or(true, |lang| *lang == joke.lang) {
+            return Ok(joke);
+        }
+        let mut conn = self.0.acquire().await?;
+        let mut translations = Self::fetch_translations(&mut conn, &joke, family_safe).await?;
+        for lang in langs {
+            if *lang == joke.lang {
+                break;
+            }
+            if let Some(i) = translations.iter().position(|t| t.lang == *lang) {
+                return Ok(translations.swap_remove(i));
+            }
+        }
         Ok(joke)
     }
 
-    pub async fn get_jokes<'a>(&self) -> Result<Vec<Joke>, JokeBaseErr> {
-        let rows = sqlx::query(r#"SELECT * FROM jokes;"#)
-            .fetch_all(&self.0)
-            .await?;
+    /// Every live joke by id, or if `family_safe` every one fit
+    /// to tell in family-safe mode.
+    pub async fn get_jokes(&self, family_safe: bool) -> Result<Vec<Joke>, JokeBaseErr> {
+        let mut conn = self.0.acquire().await?;
+        let rows = sqlx::query(
+            r#"SELECT * FROM jokes WHERE deleted IS NULL
+            AND (NOT $1 OR (audience = 'all-ages' AND cardinality(flagged) = 0))
+            ORDER BY id;"#,
+        )
+        .bind(family_safe)
+        .fetch_all(&mut *conn)
+        .await?;
         let mut jokes: Vec<Joke> = Vec::with_capacity(rows.len());
         for j in rows.iter() {
-            jokes.push(self.to_joke(j).await?);
+            jokes.push(Self::to_joke(&mut conn, j).await?);
         }
         Ok(jokes)
     }
 
-    pub async fn add(&mut self, joke: Joke) -> Result<(), JokeBaseErr> {
+    /// Live jokes most like the given one, best first.
+    pub async fn related(
+        &self,
+        index: &str,
+        limit: usize,
+        family_safe: bool,
+    ) -> Result<Vec<RelatedJoke>, JokeBaseErr> {
+        let joke = self.get(index).await?;
+        let jokes = self.get_jokes(family_safe).await?;
+        Ok(rank_related(&joke, jokes, limit))
+    }
+
+    /// The joke an old id now stands for, if it is an alias.
+    async fn alias_owner(
+        conn: &mut PgConnection,
+        alias: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let row = sqlx::query(r#"SELECT id FROM aliases WHERE alias = $1;"#)
+            .bind(alias)
+            .fetch_optional(&mut *conn)
+            .await?;
+        Ok(row.map(|row| row.get("id")))
+    }
+
+    /// True if the id belongs to a joke, trashed or not, or is
+    /// an alias.
+    async fn id_taken(conn: &mut PgConnection, id: &str) -> Result<bool, sqlx::Error> {
+        let row = sqlx::query(
+            r#"SELECT id FROM jokes WHERE id = $1
+            UNION SELECT alias FROM aliases WHERE alias = $1;"#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?;
+        Ok(row.is_some())
+    }
+
+    /// An unused id for the joke, made from its "who's there":
+    /// `olive` if that is free, then `olive-you` from the last
+    /// word of the answer, then `olive-2`, `olive-3` and so on.
+    async fn free_id(conn: &mut PgConnection, joke: &Joke) -> Result<String, sqlx::Error> {
+        let base = slugify(&joke.whos_there);
+        let worded = last_word(&joke.answer_who)
+            .map(|word| format!("{}-{}", base, word))
+            .filter(|id| id.len() <= MAX_ID_LEN && is_slug(id));
+        let numbered = (2..).map(|n| format!("{}-{}", base, n));
+        for id in std::iter::once(base.clone()).chain(worded).chain(numbered) {
+            if !Self::id_taken(conn, &id).await? {
+                return Ok(id);
+            }
+        }
+        unreachable!("ran out of numbered ids")
+    }
+
+    /// Ids of live jokes, other than this one, that look like
+    /// copies of it.
+    async fn similar_jokes(
+        conn: &mut PgConnection,
+        joke: &Joke,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"SELECT id, whos_there, answer_who FROM jokes
+            WHERE deleted IS NULL AND id <> $1
+            ORDER BY id;"#,
+        )
+        .bind(&joke.id)
+        .fetch_all(&mut *conn)
+        .await?;
+        let similar = rows.iter().filter_map(|row| {
+            let other = Joke::new(
+                row.get("id"),
+                row.get("whos_there"),
+                row.get("answer_who"),
+                &[],
+                None,
+            );
+            is_near_duplicate(joke, &other).then_some(other.id)
+        });
+        Ok(similar.collect())
+    }
+
+    /// Check a joke about to be added against the jokebase,
+    /// giving the ids of any jokes it looks like a copy of.
+    /// Under `DuplicatePolicy::Reject` having any is an error.
+    async fn check_duplicates(
+        conn: &mut PgConnection,
+        joke: &Joke,
+        policy: DuplicatePolicy,
+    ) -> Result<Vec<String>, JokeBaseErr> {
+        if policy == DuplicatePolicy::Off {
+            return Ok(Vec::new());
+        }
+        let similar = Self::similar_jokes(conn, joke).await?;
+        match similar.first() {
+            Some(id) if policy == DuplicatePolicy::Reject => {
+                Err(JokeBaseErr::DuplicateJoke(id.clone()))
+            }
+            _ => Ok(similar),
+        }
+    }
+
+    /// Groups of live jokes that look like copies of each other.
+    pub async fn find_duplicates(&self) -> Result<Vec<DuplicateCluster>, JokeBaseErr> {
+        Ok(duplicate_clusters(&self.get_jokes(false).await?))
+    }
+
+    /// Add a joke, giving back the joke as stored and the ids
+    /// of jokes it looks like a copy of, as `duplicates` allows.
+    /// A joke with an empty id is given a free one.
+    pub async fn add(
+        &mut self,
+        mut joke: Joke,
+        duplicates: DuplicatePolicy,
+        author: &str,
+    ) -> Result<(Joke, Vec<String>), JokeBaseErr> {
+        let mut tx = Pool::begin(&self.0).await?;
+        if joke.id.trim().is_empty() {
+            joke.id = Self::free_id(&mut tx, &joke).await?;
+        }
+        let mut joke = validate_joke(joke)?;
+        joke.tags = Self::resolve_tags(&mut tx, joke.tags).await?;
+        joke.translation_of = Self::resolve_translation(&mut tx, &joke).await?;
+        joke.flagged = flag_words(&joke);
+        let similar = Self::check_duplicates(&mut tx, &joke, duplicates).await?;
+        Self::insert_joke(&mut tx, &joke).await?;
+        Self::record_revision(
+            &mut tx,
+            &joke.id,
+            RevisionAction::Add,
+            author,
+            None,
+            Some(&joke),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok((joke, similar))
+    }
+
+    /// Move a joke to the trash. It keeps its tags, and can be
+    /// brought back with `undelete` until the trash is purged.
+    pub async fn delete(&mut self, index: &str, author: &str) -> Result<(), JokeBaseErr> {
         let mut tx = Pool::begin(&self.0).await?;
+        let old = Self::fetch_joke(&mut tx, index).await?;
         let result = sqlx::query(
-            r#"INSERT INTO jokes
-            (id, whos_there, answer_who, source)
-            VALUES ($1, $2, $3, $4);"#,
+            r#"UPDATE jokes
+            SET deleted = now()
+            WHERE jokes.id = $1 AND deleted IS NULL
+            RETURNING jokes.id;"#,
         )
-        .bind(&joke.id)
-        .bind(&joke.whos_there)
-        .bind(&joke.answer_who)
-        .bind(&joke.source)
-        .execute(&mut *tx)
-        .await;
+        .bind(index)
+        .fetch_all(&mut *tx)
+        .await?;
+        if result.is_empty() {
+            return Err(JokeBaseErr::JokeDoesNotExist(index.to_string()));
+        }
+        Self::record_revision(
+            &mut tx,
+            index,
+            RevisionAction::Delete,
+            author,
+            old.as_ref(),
+            None,
+        )
+        .await?;
+        Ok(tx.commit().await?)
+    }
+
+    /// Replace the joke with the given id, giving back the joke
+    /// as stored. The joke's id must stay the same: use `rename`
+    /// to change it.
+    pub async fn update(
+        &mut self,
+        index: &str,
+        joke: Joke,
+        author: &str,
+    ) -> Result<Joke, JokeBaseErr> {
+        let mut joke = validate_joke(joke)?;
+        if joke.id != index {
+            return Err(JokeBaseErr::JokeUnprocessable(format!(
+                "joke id {} does not match {}: rename the joke instead",
+                joke.id, index,
+            )));
+        }
+        let mut tx = Pool::begin(&self.0).await?;
+        joke.tags = Self::resolve_tags(&mut tx, joke.tags).await?;
+        joke.translation_of = Self::resolve_translation(&mut tx, &joke).await?;
+        joke.flagged = flag_words(&joke);
+        let old = Self::fetch_joke(&mut tx, index).await?;
+        let q = sqlx::query(
+            r#"UPDATE jokes
+            SET (whos_there, answer_who, source, script, lang, translation_of, audience, flagged) =
+                ($2, $3, $4, $5, $6, $7, $8, $9)
+            WHERE jokes.id = $1 AND deleted IS NULL
+            RETURNING jokes.id;"#,
+        );
+        let result = q
+            .bind(index)
+            .bind(&joke.whos_there)
+            .bind(&joke.answer_who)
+            .bind(&joke.source)
+            .bind(joke.script.as_ref().map(sqlx::types::Json))
+            .bind(&joke.lang)
+            .bind(&joke.translation_of)
+            .bind(joke.audience.as_str())
+            .bind(&joke.flagged)
+            .fetch_all(&mut *tx)
+            .await?;
+        if result.is_empty() {
+            return Err(JokeBaseErr::JokeDoesNotExist(index.to_string()));
+        }
+        Self::replace_tags(&mut tx, index, &joke.tags).await?;
+        Self::record_revision(
+            &mut tx,
+            index,
+            RevisionAction::Update,
+            author,
+            old.as_ref(),
+            Some(&joke),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(joke)
+    }
+
+    pub async fn history(&self, index: &str) -> Result<Vec<Revision>, JokeBaseErr> {
+        let mut conn = self.0.acquire().await?;
+        let rows = sqlx::query(r#"SELECT * FROM revisions WHERE id = $1 ORDER BY revision;"#)
+            .bind(index)
+            .fetch_all(&mut *conn)
+            .await?;
+        if rows.is_empty() && Self::fetch_joke(&mut conn, index).await?.is_none() {
+            return Err(JokeBaseErr::JokeDoesNotExist(index.to_string()));
+        }
+        let revisions: Result<Vec<Revision>, sqlx::Error> =
+            rows.iter().map(Revision::from_row).collect();
+        Ok(revisions?)
+    }
+
+    /// Put the joke back the way it is shown in the given
+    /// revision, recreating it if it has been deleted.
+    pub async fn restore(
+        &mut self,
+        index: &str,
+        revision: i32,
+        author: &str,
+    ) -> Result<Joke, JokeBaseErr> {
+        let mut tx = Pool::begin(&self.0).await?;
+        let row = sqlx::query(r#"SELECT joke FROM revisions WHERE id = $1 AND revision = $2;"#)
+            .bind(index)
+            .bind(revision)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let Some(row) = row else {
+            return Err(JokeBaseErr::RevisionDoesNotExist(
+                index.to_string(),
+                revision,
+            ));
+        };
+        let joke: Option<sqlx::types::Json<Joke>> = row.get("joke");
+        let Some(sqlx::types::Json(mut joke)) = joke else {
+            return Err(JokeBaseErr::JokeUnprocessable(format!(
+                "revision {} of joke {} has no content",
+                revision, index,
+            )));
+        };
+        // Revisions from before a rename carry the old id.
+        joke.id = index.to_string();
+        joke.flagged = flag_words(&joke);
+
+        let old = Self::fetch_joke(&mut tx, index).await?;
+        Self::upsert_joke(&mut tx, &joke).await?;
+        Self::record_revision(
+            &mut tx,
+            index,
+            RevisionAction::Restore,
+            author,
+            old.as_ref(),
+            Some(&joke),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(joke)
+    }
+
+    /// Take a joke back out of the trash.
+    pub async fn undelete(&mut self, index: &str, author: &str) -> Result<Joke, JokeBaseErr> {
+        let mut tx = Pool::begin(&self.0).await?;
+        let result = sqlx::query(
+            r#"UPDATE jokes
+            SET deleted = NULL
+            WHERE jokes.id = $1 AND deleted IS NOT NULL
+            RETURNING jokes.id;"#,
+        )
+        .bind(index)
+        .fetch_all(&mut *tx)
+        .await?;
+        if result.is_empty() {
+            return Err(JokeBaseErr::JokeDoesNotExist(index.to_string()));
+        }
+        // Its tags may have been renamed or merged while it was
+        // in the trash.
+        let mut joke = Self::fetch_joke(&mut tx, index)
+            .await?
+            .ok_or_else(|| JokeBaseErr::JokeDoesNotExist(index.to_string()))?;
+        Self::reflag(&mut tx, &mut joke).await?;
+        Self::record_revision(
+            &mut tx,
+            index,
+            RevisionAction::Restore,
+            author,
+            None,
+            Some(&joke),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(joke)
+    }
+
+    pub async fn get_trash(&self) -> Result<Vec<TrashedJoke>, JokeBaseErr> {
+        let mut conn = self.0.acquire().await?;
+        let rows =
+            sqlx::query(r#"SELECT * FROM jokes WHERE deleted IS NOT NULL ORDER BY deleted;"#)
+                .fetch_all(&mut *conn)
+                .await?;
+        let mut trash: Vec<TrashedJoke> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            trash.push(TrashedJoke {
+                joke: Self::to_joke(&mut conn, row).await?,
+                deleted: row.get("deleted"),
+            });
+        }
+        Ok(trash)
+    }
+
+    /// Permanently remove jokes that went into the trash before
+    /// `before`, returning their ids. Their revisions are kept,
+    /// so a purged joke can still be recreated from its history.
+    pub async fn purge_trash(&mut self, before: DateTime<Utc>) -> Result<Vec<String>, JokeBaseErr> {
+        // Tags, aliases, ratings, views and collection entries
+        // go with their jokes.
+        let rows = sqlx::query(r#"DELETE FROM jokes WHERE deleted < $1 RETURNING jokes.id;"#)
+            .bind(before)
+            .fetch_all(&self.0)
+            .await?;
+        Ok(rows.iter().map(|row| row.get("id")).collect())
+    }
+
+    /// Add and remove tags of a joke without touching the
+    /// rest of it.
+    pub async fn edit_tags(
+        &mut self,
+        index: &str,
+        edit: &TagEdit,
+        author: &str,
+    ) -> Result<Joke, JokeBaseErr> {
+        let mut tx = Pool::begin(&self.0).await?;
+        let old = Self::fetch_joke(&mut tx, index)
+            .await?
+            .ok_or_else(|| JokeBaseErr::JokeDoesNotExist(index.to_string()))?;
+        // Removing an alias removes the tag it stands for.
+        let remove = normalize_tags(&edit.remove);
+        let resolved = Self::resolve_tags(&mut tx, Some(remove.clone())).await?;
+        let remove: HashSet<String> = remove
+            .union(&resolved.unwrap_or_default())
+            .cloned()
+            .collect();
+        let current = old.tags.clone().unwrap_or_default();
+        let kept: HashSet<String> = current.difference(&remove).cloned().collect();
+        let add = Self::resolve_tags(&mut tx, Some(normalize_tags(&edit.add))).await?;
+        let added: HashSet<String> = add.unwrap_or_default().difference(&kept).cloned().collect();
+        let errors = check_tags(&kept.union(&added).cloned().collect());
+        if !errors.is_empty() {
+            return Err(JokeBaseErr::JokeInvalid(errors));
+        }
+        for tag in current.intersection(&remove) {
+            sqlx::query(r#"DELETE FROM tags WHERE id = $1 AND tag = $2;"#)
+                .bind(index)
+                .bind(tag)
+                .execute(&mut *tx)
+                .await?;
+        }
+        Self::insert_tags(&mut tx, index, &Some(added)).await?;
+
+        let mut joke = Self::fetch_joke(&mut tx, index)
+            .await?
+            .ok_or_else(|| JokeBaseErr::JokeDoesNotExist(index.to_string()))?;
+        Self::reflag(&mut tx, &mut joke).await?;
+        Self::record_revision(
+            &mut tx,
+            index,
+            RevisionAction::Update,
+            author,
+            Some(&old),
+            Some(&joke),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(joke)
+    }
+
+    /// Change the id of a joke, carrying its tags and history
+    /// along. The old id is kept as an alias of the new one.
+    pub async fn rename(
+        &mut self,
+        index: &str,
+        new_id: &str,
+        author: &str,
+    ) -> Result<Joke, JokeBaseErr> {
+        if let Some(error) = check_id(new_id) {
+            return Err(JokeBaseErr::JokeInvalid(vec![error]));
+        }
+        let mut tx = Pool::begin(&self.0).await?;
+        let old = Self::fetch_joke(&mut tx, index)
+            .await?
+            .ok_or_else(|| JokeBaseErr::JokeDoesNotExist(index.to_string()))?;
+
+        // A joke can take back one of its own old ids, but not
+        // one that stands for another joke.
+        match Self::alias_owner(&mut tx, new_id).await? {
+            Some(owner) if owner == old.id => {
+                sqlx::query(r#"DELETE FROM aliases WHERE alias = $1 AND id = $2;"#)
+                    .bind(new_id)
+                    .bind(&old.id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            Some(_) => return Err(JokeBaseErr::JokeExists(new_id.to_string())),
+            None => (),
+        }
+        // Tags, aliases, ratings, views and collection entries
+        // follow the joke's id.
+        let result = sqlx::query(r#"UPDATE jokes SET id = $2 WHERE id = $1;"#)
+            .bind(index)
+            .bind(new_id)
+            .execute(&mut *tx)
+            .await;
         result.map_err(|e| {
             if let sqlx::Error::Database(ref dbe) = e {
                 if let Some("23505") = dbe.code().as_deref() {
-                    return JokeBaseErr::JokeExists(joke.id.to_string());
+                    return JokeBaseErr::JokeExists(new_id.to_string());
                 }
             }
             JokeBaseErr::DatabaseError(e.to_string())
         })?;
-        Self::insert_tags(&mut tx, &joke.id, &joke.tags).await?;
-        Ok(tx.commit().await?)
-    }
-
-    pub async fn delete(&mut self, index: &str) -> Result<(), JokeBaseErr> {
-        let mut tx = Pool::begin(&self.0).await?;
-        sqlx::query(r#"DELETE FROM tags WHERE id = $1;"#)
+        sqlx::query(r#"UPDATE revisions SET id = $2 WHERE id = $1;"#)
             .bind(index)
+            .bind(new_id)
             .execute(&mut *tx)
             .await?;
-        let result = sqlx::query(r#"DELETE FROM jokes WHERE id = $1 RETURNING jokes.id;"#)
+        sqlx::query(r#"INSERT INTO aliases (alias, id) VALUES ($1, $2);"#)
             .bind(index)
+            .bind(new_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let joke = Self::fetch_joke(&mut tx, new_id)
+            .await?
+            .ok_or_else(|| JokeBaseErr::JokeDoesNotExist(new_id.to_string()))?;
+        Self::record_revision(
+            &mut tx,
+            new_id,
+            RevisionAction::Rename,
+            author,
+            Some(&old),
+            Some(&joke),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(joke)
+    }
+
+    async fn import_joke(
+        tx: &mut PgConnection,
+        joke: &Joke,
+        policy: ConflictPolicy,
+        duplicates: DuplicatePolicy,
+        author: &str,
+    ) -> Result<ImportResult, JokeBaseErr> {
+        let mut joke = validate_joke(joke.clone())?;
+        joke.tags = Self::resolve_tags(tx, joke.tags).await?;
+        joke.translation_of = Self::resolve_translation(tx, &joke).await?;
+        let joke = &joke;
+        let existing = sqlx::query(r#"SELECT id FROM jokes WHERE id = $1;"#)
+            .bind(&joke.id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let owner = Self::alias_owner(tx, &joke.id).await?;
+        if existing.is_none() && owner.is_none() {
+            let similar = match duplicates {
+                DuplicatePolicy::Off => Vec::new(),
+                _ => Self::similar_jokes(tx, joke).await?,
+            };
+            if duplicates == DuplicatePolicy::Reject && !similar.is_empty() {
+                return Ok(ImportResult::new(&joke.id, ImportStatus::Duplicate).similar_to(similar));
+            }
+            Self::insert_joke(tx, joke).await?;
+            Self::record_revision(tx, &joke.id, RevisionAction::Add, author, None, Some(joke))
+                .await?;
+            return Ok(ImportResult::new(&joke.id, ImportStatus::Added).similar_to(similar));
+        }
+        let status = match policy {
+            ConflictPolicy::Skip => ImportStatus::Skipped,
+            ConflictPolicy::Fail => return Err(JokeBaseErr::JokeExists(joke.id.clone())),
+            ConflictPolicy::Overwrite => {
+                // An old id is not overwritten: the joke it stands
+                // for has another id, and may be another joke.
+                if let Some(owner) = owner {
+                    return Err(JokeBaseErr::JokeRenamed(owner));
+                }
+                let old = Self::fetch_joke(tx, &joke.id).await?;
+                Self::upsert_joke(tx, joke).await?;
+                Self::record_revision(
+                    tx,
+                    &joke.id,
+                    RevisionAction::Update,
+                    author,
+                    old.as_ref(),
+                    Some(joke),
+                )
+                .await?;
+                ImportStatus::Overwritten
+            }
+        };
+        Ok(ImportResult::new(&joke.id, status))
+    }
+
+    /// Add many jokes, committing every `batch_size` jokes or
+    /// all at once. Under `ConflictPolicy::Fail` a failure rolls
+    /// back its batch; otherwise just the joke that failed is
+    /// left out.
+    pub async fn import(
+        &mut self,
+        jokes: &[Joke],
+        policy: ConflictPolicy,
+        duplicates: DuplicatePolicy,
+        batch_size: Option<usize>,
+        author: &str,
+    ) -> Result<Vec<ImportResult>, JokeBaseErr> {
+        let batch_size = batch_size.unwrap_or(jokes.len()).max(1);
+        let mut results: Vec<ImportResult> = Vec::with_capacity(jokes.len());
+        for batch in jokes.chunks(batch_size) {
+            let mut tx = Pool::begin(&self.0).await?;
+            let mut batch_results: Vec<ImportResult> = Vec::with_capacity(batch.len());
+            let mut failed = false;
+            for joke in batch {
+                let mut savepoint = sqlx::Connection::begin(&mut *tx).await?;
+                match Self::import_joke(&mut savepoint, joke, policy, duplicates, author).await {
+                    Ok(result) => {
+                        savepoint.commit().await?;
+                        batch_results.push(result);
+                    }
+                    Err(e) => {
+                        savepoint.rollback().await?;
+                        batch_results.push(ImportResult::failed(&joke.id, e));
+                        if policy == ConflictPolicy::Fail {
+                            failed = true;
+                            break;
+                        }
+                    }
+                }
+            }
+            if failed {
+                tx.rollback().await?;
+                for result in batch_results.iter_mut() {
+                    if result.status != ImportStatus::Failed {
+                        result.status = ImportStatus::RolledBack;
+                    }
+                }
+                for joke in &batch[batch_results.len()..] {
+                    batch_results.push(ImportResult::new(&joke.id, ImportStatus::RolledBack));
+                }
+            } else {
+                tx.commit().await?;
+            }
+            results.extend(batch_results);
+        }
+        Ok(results)
+    }
+
+    /// Every joke, including those in the trash, with its
+    /// bookkeeping.
+    pub async fn export(&self) -> Result<Vec<JokeRecord>, JokeBaseErr> {
+        let mut conn = self.0.acquire().await?;
+        let rows = sqlx::query(r#"SELECT * FROM jokes ORDER BY id;"#)
+            .fetch_all(&mut *conn)
+            .await?;
+        let mut records: Vec<JokeRecord> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let joke = Self::to_joke(&mut conn, row).await?;
+            let aliases = sqlx::query(r#"SELECT alias FROM aliases WHERE id = $1 ORDER BY alias;"#)
+                .bind(&joke.id)
+                .fetch_all(&mut *conn)
+                .await?;
+            let ratings = sqlx::query(
+                r#"SELECT voter, rating, created FROM ratings WHERE id = $1 ORDER BY voter;"#,
+            )
+            .bind(&joke.id)
+            .fetch_all(&mut *conn)
+            .await?;
+            let mut joke = joke;
+            records.push(JokeRecord {
+                flagged: Some(std::mem::take(&mut joke.flagged)),
+                joke,
+                created: row.get("created"),
+                updated: row.get("updated"),
+                deleted: row.get("deleted"),
+                aliases: aliases.iter().map(|row| row.get("alias")).collect(),
+                ratings: ratings
+                    .iter()
+                    .map(|row| VoteRecord {
+                        voter: row.get("voter"),
+                        rating: row.get("rating"),
+                        created: row.get("created"),
+                    })
+                    .collect(),
+            });
+        }
+        Ok(records)
+    }
+
+    /// Replace the whole jokebase with the given jokes, in one
+    /// transaction, if every one of them is valid. Revisions
+    /// are kept, and each joke gets a new one. Jokes in the dump
+    /// keep their views and places in collections; the rest go
+    /// with theirs. Collections, tag descriptions and tag
+    /// aliases are not part of a dump, and are left as they are.
+    pub async fn load_dump(
+        &mut self,
+        records: &[JokeRecord],
+        author: &str,
+    ) -> Result<(), JokeBaseErr> {
+        let records = validate_dump(records.to_vec())?;
+        let ids: Vec<&str> = records
+            .iter()
+            .map(|record| record.joke.id.as_str())
+            .collect();
+        let mut tx = Pool::begin(&self.0).await?;
+        let old = sqlx::query(r#"SELECT * FROM jokes WHERE deleted IS NULL;"#)
             .fetch_all(&mut *tx)
             .await?;
-        if result.len() == 0 {
-            return Err(JokeBaseErr::JokeDoesNotExist(index.to_string()));
+        let mut old_jokes: HashMap<String, Joke> = HashMap::with_capacity(old.len());
+        for row in old.iter() {
+            let joke = Self::to_joke(&mut tx, row).await?;
+            old_jokes.insert(joke.id.clone(), joke);
+        }
+        // Everything else about a joke not in the dump goes with
+        // it. Aliases and ratings of the rest come from the dump.
+        sqlx::query(r#"DELETE FROM jokes WHERE id <> ALL($1);"#)
+            .bind(&ids)
+            .execute(&mut *tx)
+            .await?;
+        for query in [r#"DELETE FROM aliases;"#, r#"DELETE FROM ratings;"#] {
+            sqlx::query(query).execute(&mut *tx).await?;
+        }
+
+        for record in &records {
+            let joke = &record.joke;
+            Self::upsert_joke(&mut tx, joke).await?;
+            let old = old_jokes.get(&joke.id);
+            Self::record_revision(
+                &mut tx,
+                &joke.id,
+                RevisionAction::Restore,
+                author,
+                old,
+                Some(joke),
+            )
+            .await?;
+            sqlx::query(
+                r#"UPDATE jokes
+                SET (deleted, flagged, created, updated) =
+                    ($2, coalesce($3, flagged), coalesce($4, created), coalesce($5, updated))
+                WHERE id = $1;"#,
+            )
+            .bind(&joke.id)
+            .bind(record.deleted)
+            .bind(&record.flagged)
+            .bind(record.created)
+            .bind(record.updated)
+            .execute(&mut *tx)
+            .await?;
+            for vote in &record.ratings {
+                sqlx::query(
+                    r#"INSERT INTO ratings (id, voter, rating, created) VALUES ($1, $2, $3, $4);"#,
+                )
+                .bind(&joke.id)
+                .bind(&vote.voter)
+                .bind(vote.rating)
+                .bind(vote.created)
+                .execute(&mut *tx)
+                .await?;
+            }
+            sqlx::query(
+                r#"UPDATE jokes
+                SET (votes, groans) =
+                    (SELECT count(*), coalesce(sum(rating), 0) FROM ratings WHERE id = $1)
+                WHERE id = $1;"#,
+            )
+            .bind(&joke.id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        for record in &records {
+            for alias in &record.aliases {
+                sqlx::query(r#"INSERT INTO aliases (alias, id) VALUES ($1, $2);"#)
+                    .bind(alias)
+                    .bind(&record.joke.id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
         }
         Ok(tx.commit().await?)
     }
 
-    pub async fn update(&mut self, index: &str, joke: Joke) -> Result<(), JokeBaseErr> {
+    /// Record a voter's rating of a live joke, replacing any
+    /// earlier rating of theirs, and give back the joke with
+    /// its new totals.
+    pub async fn rate(
+        &mut self,
+        index: &str,
+        voter: &str,
+        rating: i16,
+    ) -> Result<Joke, JokeBaseErr> {
+        check_rating(rating)?;
         let mut tx = Pool::begin(&self.0).await?;
-        let q = sqlx::query(
+        if Self::fetch_joke(&mut tx, index).await?.is_none() {
+            return Err(JokeBaseErr::JokeDoesNotExist(index.to_string()));
+        }
+        sqlx::query(
+            r#"INSERT INTO ratings (id, voter, rating) VALUES ($1, $2, $3)
+            ON CONFLICT (id, voter) DO UPDATE SET rating = $3, created = now();"#,
+        )
+        .bind(index)
+        .bind(voter)
+        .bind(rating)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
             r#"UPDATE jokes
-            SET (whos_there, answer_who, source) = ($2, $3, $4)
-            WHERE jokes.id = $1
-            RETURNING jokes.id;"#,
-        );
-        let result = q.bind(&joke.id)
-            .bind(&joke.whos_there)
-            .bind(&joke.answer_who)
-            .bind(&joke.source)
+            SET (votes, groans) =
+                (SELECT count(*), coalesce(sum(rating), 0) FROM ratings WHERE id = $1)
+            WHERE id = $1;"#,
+        )
+        .bind(index)
+        .execute(&mut *tx)
+        .await?;
+        let joke = Self::fetch_joke(&mut tx, index)
+            .await?
+            .ok_or_else(|| JokeBaseErr::JokeDoesNotExist(index.to_string()))?;
+        tx.commit().await?;
+        Ok(joke)
+    }
+
+    /// Best rated live jokes with at least `min_votes` votes,
+    /// best first.
+    pub async fn top_rated(
+        &self,
+        limit: i64,
+        min_votes: i32,
+        family_safe: bool,
+    ) -> Result<Vec<Joke>, JokeBaseErr> {
+        let mut conn = self.0.acquire().await?;
+        let rows = sqlx::query(
+            r#"SELECT * FROM jokes
+            WHERE deleted IS NULL AND votes > 0 AND votes >= $2
+            AND (NOT $3 OR (audience = 'all-ages' AND cardinality(flagged) = 0))
+            ORDER BY groans::float8 / votes DESC, votes DESC, id
+            LIMIT $1;"#,
+        )
+        .bind(limit)
+        .bind(min_votes)
+        .bind(family_safe)
+        .fetch_all(&mut *conn)
+        .await?;
+        let mut jokes: Vec<Joke> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            jokes.push(Self::to_joke(&mut conn, row).await?);
+        }
+        Ok(jokes)
+    }
+
+    /// Live jokes the wordlist flagged, waiting for review.
+    pub async fn flagged_jokes(&self) -> Result<Vec<Joke>, JokeBaseErr> {
+        let mut conn = self.0.acquire().await?;
+        let rows = sqlx::query(
+            r#"SELECT * FROM jokes
+            WHERE deleted IS NULL AND cardinality(flagged) > 0
+            ORDER BY updated, id;"#,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        let mut jokes: Vec<Joke> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            jokes.push(Self::to_joke(&mut conn, row).await?);
+        }
+        Ok(jokes)
+    }
+
+    /// Check every live joke against the wordlist again, as
+    /// after it has changed, reviewed jokes included. Gives back
+    /// the jokes left flagged.
+    pub async fn flag_all(&mut self) -> Result<Vec<Joke>, JokeBaseErr> {
+        let mut tx = Pool::begin(&self.0).await?;
+        let rows = sqlx::query(r#"SELECT * FROM jokes WHERE deleted IS NULL ORDER BY id;"#)
             .fetch_all(&mut *tx)
             .await?;
-        if result.len() == 0 {
-            return Err(JokeBaseErr::JokeDoesNotExist(index.to_string()));
+        let mut flagged: Vec<Joke> = Vec::new();
+        for row in rows.iter() {
+            let mut joke = Self::to_joke(&mut tx, row).await?;
+            Self::reflag(&mut tx, &mut joke).await?;
+            if !joke.flagged.is_empty() {
+                flagged.push(joke);
+            }
         }
-        sqlx::query(r#"DELETE FROM tags WHERE id = $1;"#)
+        tx.commit().await?;
+        Ok(flagged)
+    }
+
+    /// Settle the audience of a live joke, clearing any words
+    /// flagged in it. Words are checked again when it is next
+    /// changed.
+    pub async fn review(
+        &mut self,
+        index: &str,
+        audience: Audience,
+        author: &str,
+    ) -> Result<Joke, JokeBaseErr> {
+        let mut tx = Pool::begin(&self.0).await?;
+        let old = Self::fetch_joke(&mut tx, index)
+            .await?
+            .ok_or_else(|| JokeBaseErr::JokeDoesNotExist(index.to_string()))?;
+        sqlx::query(r#"UPDATE jokes SET (audience, flagged) = ($2, '{}') WHERE id = $1;"#)
             .bind(index)
+            .bind(audience.as_str())
             .execute(&mut *tx)
             .await?;
-        Self::insert_tags(&mut tx, &joke.id, &joke.tags).await?;
-        Ok(tx.commit().await?)
+        let joke = Joke {
+            audience,
+            flagged: Vec::new(),
+            ..old.clone()
+        };
+        Self::record_revision(
+            &mut tx,
+            index,
+            RevisionAction::Update,
+            author,
+            Some(&old),
+            Some(&joke),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(joke)
+    }
+
+    /// Those of the given ids that are already taken, by a joke
+    /// (trashed or not) or an alias.
+    pub async fn taken_ids(&self, ids: &[String]) -> Result<Vec<String>, JokeBaseErr> {
+        let rows = sqlx::query(
+            r#"SELECT id FROM jokes WHERE id = ANY($1)
+            UNION SELECT alias FROM aliases WHERE alias = ANY($1)
+            ORDER BY id;"#,
+        )
+        .bind(ids)
+        .fetch_all(&self.0)
+        .await?;
+        Ok(rows.iter().map(|row| row.get("id")).collect())
+    }
+
+    /// Store views queued by the analytics writer. Views of
+    /// jokes purged or renamed since they were told are dropped.
+    pub async fn record_views(&self, views: &[View]) -> Result<(), JokeBaseErr> {
+        let ids: Vec<&str> = views.iter().map(|v| v.id.as_str()).collect();
+        let channels: Vec<&str> = views.iter().map(|v| v.channel.as_str()).collect();
+        let served: Vec<DateTime<Utc>> = views.iter().map(|v| v.served).collect();
+        sqlx::query(
+            r#"INSERT INTO views (id, channel, served)
+            SELECT v.* FROM UNNEST($1::text[], $2::text[], $3::timestamptz[]) AS v (id, channel, served)
+            JOIN jokes j ON j.id = v.id;"#,
+        )
+        .bind(ids)
+        .bind(channels)
+        .bind(served)
+        .execute(&self.0)
+        .await?;
+        Ok(())
+    }
+
+    /// How often each joke was told since the given time, most
+    /// told first.
+    pub async fn view_counts(
+        &self,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<ViewCount>, JokeBaseErr> {
+        let rows = sqlx::query(
+            r#"SELECT id, channel, count(*) AS views,
+                sum(count(*)) OVER (PARTITION BY id)::int8 AS total
+            FROM views WHERE served >= $1
+            GROUP BY id, channel
+            ORDER BY total DESC, id, channel;"#,
+        )
+        .bind(since)
+        .fetch_all(&self.0)
+        .await?;
+        let mut counts: Vec<ViewCount> = Vec::new();
+        for row in rows.iter() {
+            let id: String = row.get("id");
+            if counts.last().map(|c| &c.id) != Some(&id) {
+                if counts.len() as i64 == limit {
+                    break;
+                }
+                counts.push(ViewCount {
+                    id,
+                    total: row.get("total"),
+                    channels: BTreeMap::new(),
+                });
+            }
+            let count = counts.last_mut().unwrap();
+            count.channels.insert(row.get("channel"), row.get("views"));
+        }
+        Ok(counts)
+    }
+
+    /// Views of a joke since the given time, by channel and
+    /// time bucket, oldest first.
+    pub async fn view_series(
+        &self,
+        index: &str,
+        bucket: Bucket,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<ViewPoint>, JokeBaseErr> {
+        let rows = sqlx::query(
+            r#"SELECT date_trunc($2, served) AS period, channel, count(*) AS views
+            FROM views WHERE id = $1 AND served >= $3
+            GROUP BY period, channel
+            ORDER BY period, channel;"#,
+        )
+        .bind(index)
+        .bind(bucket.as_str())
+        .bind(since)
+        .fetch_all(&self.0)
+        .await?;
+        Ok(rows
+            .iter()
+            .map(|row| ViewPoint {
+                period: row.get("period"),
+                channel: row.get("channel"),
+                views: row.get("views"),
+            })
+            .collect())
+    }
+
+    async fn fetch_collection(
+        conn: &mut PgConnection,
+        index: &str,
+    ) -> Result<Option<Collection>, sqlx::Error> {
+        let row = sqlx::query(r#"SELECT * FROM collections WHERE id = $1;"#)
+            .bind(index)
+            .fetch_optional(&mut *conn)
+            .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let jokes = sqlx::query(
+            r#"SELECT id FROM collection_jokes WHERE collection = $1 ORDER BY position;"#,
+        )
+        .bind(index)
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(Some(Collection {
+            id: row.get("id"),
+            name: row.get("name"),
+            description: row.get("description"),
+            jokes: jokes.iter().map(|row| row.get("id")).collect(),
+        }))
+    }
+
+    /// Check that every joke of the collection is live, and
+    /// store its jokes in order in place of any old ones.
+    async fn insert_collection_jokes(
+        tx: &mut PgConnection,
+        collection: &Collection,
+    ) -> Result<(), JokeBaseErr> {
+        let rows = sqlx::query(r#"SELECT id FROM jokes WHERE id = ANY($1) AND deleted IS NULL;"#)
+            .bind(&collection.jokes)
+            .fetch_all(&mut *tx)
+            .await?;
+        let live: HashSet<String> = rows.iter().map(|row| row.get("id")).collect();
+        let errors: Vec<FieldError> = collection
+            .jokes
+            .iter()
+            .filter(|id| !live.contains(*id))
+            .map(|id| FieldError::new("jokes", format!("joke {} doesn't exist", id)))
+            .collect();
+        if !errors.is_empty() {
+            return Err(JokeBaseErr::CollectionInvalid(errors));
+        }
+
+        sqlx::query(r#"DELETE FROM collection_jokes WHERE collection = $1;"#)
+            .bind(&collection.id)
+            .execute(&mut *tx)
+            .await?;
+        let positions: Vec<i32> = (0..collection.jokes.len() as i32).collect();
+        sqlx::query(
+            r#"INSERT INTO collection_jokes (collection, position, id)
+            SELECT $1, * FROM UNNEST($2::int4[], $3::text[]);"#,
+        )
+        .bind(&collection.id)
+        .bind(positions)
+        .bind(&collection.jokes)
+        .execute(&mut *tx)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_collections(&self) -> Result<Vec<Collection>, JokeBaseErr> {
+        let mut conn = self.0.acquire().await?;
+        let rows = sqlx::query(r#"SELECT id FROM collections ORDER BY id;"#)
+            .fetch_all(&mut *conn)
+            .await?;
+        let mut collections = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let id: String = row.get("id");
+            collections.extend(Self::fetch_collection(&mut conn, &id).await?);
+        }
+        Ok(collections)
+    }
+
+    pub async fn get_collection(&self, index: &str) -> Result<Collection, JokeBaseErr> {
+        let mut conn = self.0.acquire().await?;
+        Self::fetch_collection(&mut conn, index)
+            .await?
+            .ok_or_else(|| JokeBaseErr::CollectionDoesNotExist(index.to_string()))
+    }
+
+    /// Add a collection, giving back the collection as stored.
+    pub async fn add_collection(
+        &mut self,
+        collection: Collection,
+    ) -> Result<Collection, JokeBaseErr> {
+        let collection = validate_collection(collection)?;
+        let mut tx = Pool::begin(&self.0).await?;
+        let result =
+            sqlx::query(r#"INSERT INTO collections (id, name, description) VALUES ($1, $2, $3);"#)
+                .bind(&collection.id)
+                .bind(&collection.name)
+                .bind(&collection.description)
+                .execute(&mut *tx)
+                .await;
+        result.map_err(|e| {
+            if let sqlx::Error::Database(ref dbe) = e {
+                if let Some("23505") = dbe.code().as_deref() {
+                    return JokeBaseErr::CollectionExists(collection.id.to_string());
+                }
+            }
+            JokeBaseErr::DatabaseError(e.to_string())
+        })?;
+        Self::insert_collection_jokes(&mut tx, &collection).await?;
+        tx.commit().await?;
+        Ok(collection)
+    }
+
+    /// Replace the collection with the given id, giving back the
+    /// collection as stored.
+    pub async fn update_collection(
+        &mut self,
+        index: &str,
+        collection: Collection,
+    ) -> Result<Collection, JokeBaseErr> {
+        let collection = validate_collection(collection)?;
+        if collection.id != index {
+            let message = format!("must match collection {}", index);
+            return Err(JokeBaseErr::CollectionInvalid(vec![FieldError::new(
+                "id", message,
+            )]));
+        }
+        let mut tx = Pool::begin(&self.0).await?;
+        let result =
+            sqlx::query(r#"UPDATE collections SET (name, description) = ($2, $3) WHERE id = $1;"#)
+                .bind(index)
+                .bind(&collection.name)
+                .bind(&collection.description)
+                .execute(&mut *tx)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Err(JokeBaseErr::CollectionDoesNotExist(index.to_string()));
+        }
+        Self::insert_collection_jokes(&mut tx, &collection).await?;
+        tx.commit().await?;
+        Ok(collection)
+    }
+
+    pub async fn delete_collection(&mut self, index: &str) -> Result<(), JokeBaseErr> {
+        let result = sqlx::query(r#"DELETE FROM collections WHERE id = $1;"#)
+            .bind(index)
+            .execute(&self.0)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(JokeBaseErr::CollectionDoesNotExist(index.to_string()));
+        }
+        Ok(())
+    }
+
+    /// A live joke from the collection: the one at `position`,
+    /// counting round from the start again past the end, or a
+    /// random one. Gives back the joke and its position. If
+    /// `family_safe`, jokes not fit for it are skipped.
+    pub async fn collection_joke(
+        &self,
+        index: &str,
+        order: CollectionOrder,
+        position: usize,
+        family_safe: bool,
+    ) -> Result<(Joke, usize), JokeBaseErr> {
+        let mut conn = self.0.acquire().await?;
+        let collection = sqlx::query(r#"SELECT id FROM collections WHERE id = $1;"#)
+            .bind(index)
+            .fetch_optional(&mut *conn)
+            .await?;
+        if collection.is_none() {
+            return Err(JokeBaseErr::CollectionDoesNotExist(index.to_string()));
+        }
+        let rows = sqlx::query(
+            r#"SELECT c.id FROM collection_jokes c
+            JOIN jokes j ON j.id = c.id AND j.deleted IS NULL
+            WHERE c.collection = $1
+            AND (NOT $2 OR (j.audience = 'all-ages' AND cardinality(j.flagged) = 0))
+            ORDER BY c.position;"#,
+        )
+        .bind(index)
+        .bind(family_safe)
+        .fetch_all(&mut *conn)
+        .await?;
+        if rows.is_empty() {
+            return Err(JokeBaseErr::NoJoke);
+        }
+        let position = match order {
+            CollectionOrder::Sequential => position % rows.len(),
+            CollectionOrder::Random => {
+                let row = sqlx::query(r#"SELECT floor(random() * $1)::int8 AS n;"#)
+                    .bind(rows.len() as i64)
+                    .fetch_one(&mut *conn)
+                    .await?;
+                row.get::<i64, _>("n") as usize
+            }
+        };
+        let id: String = rows[position].get("id");
+        let joke = Self::fetch_joke(&mut conn, &id)
+            .await?
+            .ok_or(JokeBaseErr::JokeDoesNotExist(id))?;
+        Ok((joke, position))
+    }
+
+    /// Every tag in use, described or aliased, with how many live
+    /// jokes have it.
+    pub async fn tag_list(&self) -> Result<Vec<TagInfo>, JokeBaseErr> {
+        let mut conn = self.0.acquire().await?;
+        let counts = sqlx::query(
+            r#"SELECT t.tag, count(DISTINCT t.id) AS count
+            FROM tags t JOIN jokes j ON j.id = t.id AND j.deleted IS NULL
+            GROUP BY t.tag;"#,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        let descriptions = sqlx::query(r#"SELECT tag, description FROM tag_descriptions;"#)
+            .fetch_all(&mut *conn)
+            .await?;
+        let aliases = sqlx::query(r#"SELECT alias, tag FROM tag_aliases ORDER BY alias;"#)
+            .fetch_all(&mut *conn)
+            .await?;
+
+        let mut tags: BTreeMap<String, TagInfo> = BTreeMap::new();
+        fn entry<'a>(tags: &'a mut BTreeMap<String, TagInfo>, row: &PgRow) -> &'a mut TagInfo {
+            let tag: String = row.get("tag");
+            tags.entry(tag.clone())
+                .or_insert_with(|| TagInfo::unused(tag))
+        }
+        for row in counts.iter() {
+            entry(&mut tags, row).count = row.get("count");
+        }
+        for row in descriptions.iter() {
+            entry(&mut tags, row).description = Some(row.get("description"));
+        }
+        for row in aliases.iter() {
+            entry(&mut tags, row).aliases.push(row.get("alias"));
+        }
+        Ok(tags.into_values().collect())
+    }
+
+    /// How the tag is used. A tag nothing uses has a count of 0.
+    pub async fn tag_info(&self, tag: &str) -> Result<TagInfo, JokeBaseErr> {
+        let info = self
+            .tag_list()
+            .await?
+            .into_iter()
+            .find(|info| info.tag == tag);
+        Ok(info.unwrap_or_else(|| TagInfo::unused(tag.to_string())))
+    }
+
+    /// True if any joke, trashed or not, has the tag or it has a
+    /// description.
+    async fn tag_exists(conn: &mut PgConnection, tag: &str) -> Result<bool, sqlx::Error> {
+        let row = sqlx::query(
+            r#"SELECT tag FROM tags WHERE tag = $1
+            UNION SELECT tag FROM tag_descriptions WHERE tag = $1;"#,
+        )
+        .bind(tag)
+        .fetch_optional(&mut *conn)
+        .await?;
+        Ok(row.is_some())
+    }
+
+    async fn tag_target(conn: &mut PgConnection, tag: &str) -> Result<Option<String>, sqlx::Error> {
+        let row = sqlx::query(r#"SELECT tag FROM tag_aliases WHERE alias = $1;"#)
+            .bind(tag)
+            .fetch_optional(&mut *conn)
+            .await?;
+        Ok(row.map(|row| row.get("tag")))
+    }
+
+    pub async fn describe_tag(
+        &mut self,
+        tag: &str,
+        description: Option<&str>,
+    ) -> Result<TagInfo, JokeBaseErr> {
+        let tag = normalize_tag(tag);
+        let description = description.map(str::trim).filter(|d| !d.is_empty());
+        let mut errors: Vec<FieldError> = check_tag_name("tag", &tag).into_iter().collect();
+        if let Some(description) = description {
+            errors.extend(check_text(
+                "description",
+                description,
+                MAX_TAG_DESCRIPTION_LEN,
+            ));
+        }
+        tag_errors(errors)?;
+        let mut tx = Pool::begin(&self.0).await?;
+        if let Some(target) = Self::tag_target(&mut tx, &tag).await? {
+            let message = format!("is an alias of {}: describe that instead", target);
+            return Err(JokeBaseErr::TagInvalid(vec![FieldError::new(
+                "tag", message,
+            )]));
+        }
+        match description {
+            Some(description) => {
+                sqlx::query(
+                    r#"INSERT INTO tag_descriptions (tag, description) VALUES ($1, $2)
+                    ON CONFLICT (tag) DO UPDATE SET description = $2;"#,
+                )
+                .bind(&tag)
+                .bind(description)
+                .execute(&mut *tx)
+                .await?;
+            }
+            None => {
+                sqlx::query(r#"DELETE FROM tag_descriptions WHERE tag = $1;"#)
+                    .bind(&tag)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+        self.tag_info(&tag).await
+    }
+
+    /// Replace each of the `from` tags with `into` on every joke,
+    /// recording a revision for each live joke changed, and make
+    /// them aliases of `into`. `into` keeps its description, or
+    /// takes one of theirs if it has none.
+    async fn fold_tags(
+        tx: &mut PgConnection,
+        from: &[String],
+        into: &str,
+        author: &str,
+    ) -> Result<(), JokeBaseErr> {
+        let rows = sqlx::query(r#"SELECT DISTINCT id FROM tags WHERE tag = ANY($1) ORDER BY id;"#)
+            .bind(from)
+            .fetch_all(&mut *tx)
+            .await?;
+        for row in rows.iter() {
+            let id: String = row.get("id");
+            let old = Self::fetch_joke(tx, &id).await?;
+            sqlx::query(r#"DELETE FROM tags WHERE id = $1 AND tag = ANY($2);"#)
+                .bind(&id)
+                .bind(from)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(r#"INSERT INTO tags (id, tag) VALUES ($1, $2) ON CONFLICT DO NOTHING;"#)
+                .bind(&id)
+                .bind(into)
+                .execute(&mut *tx)
+                .await?;
+            if let Some(old) = old {
+                let mut new = Self::fetch_joke(tx, &id).await?;
+                if let Some(joke) = new.as_mut() {
+                    Self::reflag(tx, joke).await?;
+                }
+                Self::record_revision(
+                    tx,
+                    &id,
+                    RevisionAction::Update,
+                    author,
+                    Some(&old),
+                    new.as_ref(),
+                )
+                .await?;
+            }
+        }
+
+        sqlx::query(
+            r#"INSERT INTO tag_descriptions (tag, description)
+            SELECT $2, description FROM tag_descriptions WHERE tag = ANY($1)
+            ORDER BY tag LIMIT 1
+            ON CONFLICT (tag) DO NOTHING;"#,
+        )
+        .bind(from)
+        .bind(into)
+        .execute(&mut *tx)
+        .await?;
+        for query in [
+            r#"DELETE FROM tag_descriptions WHERE tag = ANY($1);"#,
+            r#"UPDATE tag_aliases SET tag = $2 WHERE tag = ANY($1);"#,
+            r#"DELETE FROM tag_aliases WHERE alias = $2;"#,
+            r#"INSERT INTO tag_aliases (alias, tag) SELECT UNNEST($1::text[]), $2
+            ON CONFLICT (alias) DO UPDATE SET tag = EXCLUDED.tag;"#,
+        ] {
+            sqlx::query(query)
+                .bind(from)
+                .bind(into)
+                .execute(&mut *tx)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Give a tag a new name that isn't in use yet. The old name
+    /// becomes an alias of the new one.
+    pub async fn rename_tag(
+        &mut self,
+        tag: &str,
+        to: &str,
+        author: &str,
+    ) -> Result<TagInfo, JokeBaseErr> {
+        let (tag, to) = (normalize_tag(tag), normalize_tag(to));
+        let mut errors: Vec<FieldError> = [check_tag_name("tag", &tag), check_tag_name("to", &to)]
+            .into_iter()
+            .flatten()
+            .collect();
+        if errors.is_empty() && tag == to {
+            errors.push(FieldError::new("to", "must differ from the tag"));
+        }
+        tag_errors(errors)?;
+        let mut tx = Pool::begin(&self.0).await?;
+        if !Self::tag_exists(&mut tx, &tag).await? {
+            return Err(JokeBaseErr::TagDoesNotExist(tag));
+        }
+        if Self::tag_exists(&mut tx, &to).await? {
+            return Err(JokeBaseErr::TagExists(to));
+        }
+        if let Some(target) = Self::tag_target(&mut tx, &to).await? {
+            let message = format!("is an alias of {}: delete the alias first", target);
+            return Err(JokeBaseErr::TagInvalid(vec![FieldError::new(
+                "to", message,
+            )]));
+        }
+        Self::fold_tags(&mut tx, &[tag], &to, author).await?;
+        tx.commit().await?;
+        self.tag_info(&to).await
+    }
+
+    /// Fold several tags into one, such as `animals` into
+    /// `animal`. The merged tags become aliases of `into`.
+    pub async fn merge_tags(
+        &mut self,
+        merge: &TagMerge,
+        author: &str,
+    ) -> Result<TagInfo, JokeBaseErr> {
+        let into = normalize_tag(&merge.into);
+        let mut from: Vec<String> = merge.tags.iter().map(|tag| normalize_tag(tag)).collect();
+        from.sort();
+        from.dedup();
+        from.retain(|tag| *tag != into);
+        let mut errors: Vec<FieldError> = check_tag_name("into", &into).into_iter().collect();
+        errors.extend(from.iter().filter_map(|tag| check_tag_name("tags", tag)));
+        if from.is_empty() {
+            errors.push(FieldError::new("tags", "must name a tag other than into"));
+        }
+        tag_errors(errors)?;
+        let mut tx = Pool::begin(&self.0).await?;
+        for tag in &from {
+            if !Self::tag_exists(&mut tx, tag).await? {
+                return Err(JokeBaseErr::TagDoesNotExist(tag.clone()));
+            }
+        }
+        if let Some(target) = Self::tag_target(&mut tx, &into).await? {
+            let message = format!("is an alias of {}: merge into that instead", target);
+            return Err(JokeBaseErr::TagInvalid(vec![FieldError::new(
+                "into", message,
+            )]));
+        }
+        Self::fold_tags(&mut tx, &from, &into, author).await?;
+        tx.commit().await?;
+        self.tag_info(&into).await
+    }
+
+    pub async fn tag_aliases(&self) -> Result<Vec<TagAlias>, JokeBaseErr> {
+        let rows = sqlx::query(r#"SELECT alias, tag FROM tag_aliases ORDER BY alias;"#)
+            .fetch_all(&self.0)
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| TagAlias {
+                alias: row.get("alias"),
+                tag: row.get("tag"),
+            })
+            .collect())
+    }
+
+    /// Make `alias` stand for `tag` in jokes written from now on.
+    /// An alias can't be a tag in use: merge it instead.
+    pub async fn set_tag_alias(&mut self, alias: &str, tag: &str) -> Result<TagAlias, JokeBaseErr> {
+        let (alias, tag) = (normalize_tag(alias), normalize_tag(tag));
+        let errors: Vec<FieldError> =
+            [check_tag_name("alias", &alias), check_tag_name("tag", &tag)]
+                .into_iter()
+                .flatten()
+                .collect();
+        tag_errors(errors)?;
+        let mut tx = Pool::begin(&self.0).await?;
+        let tag = Self::tag_target(&mut tx, &tag).await?.unwrap_or(tag);
+        if tag == alias {
+            let message = "must not stand for itself";
+            return Err(JokeBaseErr::TagInvalid(vec![FieldError::new(
+                "alias", message,
+            )]));
+        }
+        if Self::tag_exists(&mut tx, &alias).await? {
+            return Err(JokeBaseErr::TagExists(alias));
+        }
+        for query in [
+            r#"UPDATE tag_aliases SET tag = $2 WHERE tag = $1;"#,
+            r#"INSERT INTO tag_aliases (alias, tag) VALUES ($1, $2)
+            ON CONFLICT (alias) DO UPDATE SET tag = $2;"#,
+        ] {
+            sqlx::query(query)
+                .bind(&alias)
+                .bind(&tag)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(TagAlias { alias, tag })
+    }
+
+    pub async fn delete_tag_alias(&mut self, alias: &str) -> Result<(), JokeBaseErr> {
+        let result = sqlx::query(r#"DELETE FROM tag_aliases WHERE alias = $1;"#)
+            .bind(normalize_tag(alias))
+            .execute(&self.0)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(JokeBaseErr::TagDoesNotExist(alias.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Check jokes read from text against the jokebase, and
+    /// import them unless this is a dry run.
+    pub async fn import_text(
+        &mut self,
+        parsed: TextJokes,
+        dry_run: bool,
+        policy: ConflictPolicy,
+        duplicates: DuplicatePolicy,
+        batch_size: Option<usize>,
+        author: &str,
+    ) -> Result<TextImportReport, JokeBaseErr> {
+        let ids: Vec<String> = parsed.jokes.iter().map(|j| j.id.clone()).collect();
+        let collisions = self.taken_ids(&ids).await?;
+        let results = if dry_run {
+            None
+        } else {
+            Some(
+                self.import(&parsed.jokes, policy, duplicates, batch_size, author)
+                    .await?,
+            )
+        };
+        Ok(TextImportReport {
+            parsed,
+            collisions,
+            results,
+        })
     }
 }
@@ -0,0 +1,241 @@
+//! Languages jokes are told in. A joke may be a translation of
+//! another, its original; jokes are served in the language the
+//! client prefers when there is a translation into it, and as
+//! written otherwise.
+
+use crate::*;
+
+pub const DEFAULT_LANG: &str = "en";
+
+/// The fixed lines of a knock-knock joke in one language.
+struct Phrases {
+    lang: &'static str,
+    knock_knock: &'static str,
+    whos_there: &'static str,
+    /// Text before and after the name in "Boo who?".
+    who: (&'static str, &'static str),
+}
+
+const PHRASES: [Phrases; 4] = [
+    Phrases {
+        lang: "en",
+        knock_knock: "Knock knock!",
+        whos_there: "Who's there?",
+        who: ("", " who?"),
+    },
+    Phrases {
+        lang: "es",
+        knock_knock: "¡Toc, toc!",
+        whos_there: "¿Quién es?",
+        who: ("¿", " quién?"),
+    },
+    Phrases {
+        lang: "fr",
+        knock_knock: "Toc, toc !",
+        whos_there: "Qui est là ?",
+        who: ("", " qui ?"),
+    },
+    Phrases {
+        lang: "de",
+        knock_knock: "Klopf, klopf!",
+        whos_there: "Wer ist da?",
+        who: ("", " wer?"),
+    },
+];
+
+fn phrases(lang: &str) -> &'static Phrases {
+    PHRASES
+        .iter()
+        .find(|p| p.lang == lang)
+        .unwrap_or(&PHRASES[0])
+}
+
+pub fn default_lang() -> String {
+    DEFAULT_LANG.to_string()
+}
+
+/// Codes of the languages jokes can be written in.
+pub fn languages() -> impl Iterator<Item = &'static str> {
+    PHRASES.iter().map(|p| p.lang)
+}
+
+pub fn knock_knock_line(lang: &str) -> &'static str {
+    phrases(lang).knock_knock
+}
+
+pub fn whos_there_line(lang: &str) -> &'static str {
+    phrases(lang).whos_there
+}
+
+/// "Boo who?", in the given language.
+pub fn who_who_line(lang: &str, who: &str) -> String {
+    let (before, after) = phrases(lang).who;
+    format!("{}{}{}", before, who, after)
+}
+
+fn first_word(line: &str) -> String {
+    line.split(|c: char| !c.is_alphanumeric())
+        .find(|word| !word.is_empty())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Whether a line opens a knock-knock joke in any language.
+pub fn is_knock_knock(line: &str) -> bool {
+    let word = first_word(line);
+    PHRASES.iter().any(|p| first_word(p.knock_knock) == word)
+}
+
+/// Whether a line asks who's there in any language.
+pub fn is_whos_there(line: &str) -> bool {
+    let word = first_word(line);
+    PHRASES.iter().any(|p| first_word(p.whos_there) == word)
+}
+
+/// Whether a line asks "Boo who?" of `who` in any language,
+/// ignoring case and quotes.
+pub fn is_who_who(line: &str, who: &str) -> bool {
+    let line = line.replace('"', "").to_lowercase();
+    PHRASES
+        .iter()
+        .any(|p| who_who_line(p.lang, who).to_lowercase() == line)
+}
+
+pub fn check_lang(lang: &str) -> Option<FieldError> {
+    if languages().any(|l| l == lang) {
+        return None;
+    }
+    let languages: Vec<&str> = languages().collect();
+    Some(FieldError::from_key(
+        "lang",
+        "field.one_of",
+        &[&languages.join(", ")],
+    ))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct LangParams {
+    /// Language to tell the joke in, ahead of those in
+    /// `Accept-Language`.
+    #[param(example = "es")]
+    pub lang: Option<String>,
+}
+
+impl LangParams {
+    /// Languages to try, best first.
+    pub fn preferences(&self, accepted: AcceptLanguage) -> Vec<String> {
+        let mut langs: Vec<String> = self
+            .lang
+            .iter()
+            .map(|lang| lang.trim().to_lowercase())
+            .collect();
+        for lang in accepted.0 {
+            if !langs.contains(&lang) {
+                langs.push(lang);
+            }
+        }
+        langs
+    }
+}
+
+/// Primary language codes from the request's `Accept-Language`
+/// header, best first.
+#[derive(Debug, Clone, Default)]
+pub struct AcceptLanguage(pub Vec<String>);
+
+impl AcceptLanguage {
+    pub fn parse(header: &str) -> Self {
+        let mut ranges: Vec<(String, f32)> = header
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';').map(str::trim);
+                let tag = parts.next()?.split('-').next()?.to_lowercase();
+                let q = parts
+                    .find_map(|p| p.strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse().ok())?;
+                (!tag.is_empty() && tag != "*" && q > 0.0).then_some((tag, q))
+            })
+            .collect();
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let mut langs: Vec<String> = Vec::with_capacity(ranges.len());
+        for (tag, _) in ranges {
+            if !langs.contains(&tag) {
+                langs.push(tag);
+            }
+        }
+        AcceptLanguage(langs)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AcceptLanguage {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = parts
+            .headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok());
+        Ok(header.map(AcceptLanguage::parse).unwrap_or_default())
+    }
+}
+
+/// Cookie keeping the language picked on the web with `?lang=`.
+const LANG_COOKIE: &str = "lang";
+const LANG_COOKIE_MAX_AGE: i64 = 365 * 24 * 60 * 60;
+
+/// Whether `lang` looks like a language code, and so is safe
+/// to keep in a cookie.
+fn is_lang_code(lang: &str) -> bool {
+    (1..=8).contains(&lang.len()) && lang.chars().all(|c| c.is_ascii_lowercase())
+}
+
+/// The language picked earlier on the web, from the request's
+/// cookie.
+#[derive(Debug, Clone, Default)]
+pub struct LangCookie(pub Option<String>);
+
+impl LangCookie {
+    /// `Set-Cookie` header keeping `lang`, if it is a language
+    /// code.
+    pub fn set(lang: &str) -> Option<(header::HeaderName, String)> {
+        let lang = lang.trim().to_lowercase();
+        is_lang_code(&lang).then(|| {
+            let cookie = format!(
+                "{}={}; Path=/; Max-Age={}; SameSite=Lax",
+                LANG_COOKIE, lang, LANG_COOKIE_MAX_AGE,
+            );
+            (header::SET_COOKIE, cookie)
+        })
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for LangCookie {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let lang = parts
+            .headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, _)| *name == LANG_COOKIE)
+            .map(|(_, lang)| lang.to_string())
+            .filter(|lang| is_lang_code(lang));
+        Ok(LangCookie(lang))
+    }
+}
+
+/// Request headers a web page's language is chosen by.
+pub const PAGE_LANG_VARY: &str = "Accept-Language, Cookie";
+
+/// Headers telling caches the joke was picked by language.
+pub fn language_headers(joke: &Joke) -> AppendHeaders<[(header::HeaderName, String); 2]> {
+    AppendHeaders([
+        (header::CONTENT_LANGUAGE, joke.lang.clone()),
+        (header::VARY, "Accept-Language".to_string()),
+    ])
+}
@@ -1,40 +1,83 @@
+mod analytics;
 mod api;
 mod appstate;
+mod audience;
 mod authjwt;
+mod cli;
+mod collections;
+mod dump;
+mod duplicates;
+mod fortune;
+mod import;
 mod joke;
 mod jokebase;
+mod lang;
+mod messages;
+mod negotiate;
+mod patch;
+mod problem;
+mod ratings;
+mod related;
+mod revision;
+mod script;
 mod startup;
+mod tags;
+mod textjokes;
+mod trash;
+mod validate;
 mod web;
 
+use analytics::*;
 use api::*;
 use appstate::*;
+use audience::*;
 use authjwt::*;
+use cli::*;
+use collections::*;
+use dump::*;
+use duplicates::*;
+use fortune::*;
+use import::*;
 use joke::*;
 use jokebase::*;
+use lang::*;
+use messages::*;
+use negotiate::*;
+use patch::*;
+use problem::*;
+use ratings::*;
+use related::*;
+use revision::*;
+use script::*;
 use startup::*;
+use tags::*;
+use textjokes::*;
+use trash::*;
+use validate::*;
 use web::*;
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error::Error;
 use std::sync::Arc;
 
 use askama::Template;
 use axum::{
     async_trait,
-    extract::{FromRequestParts, Path, Query, State},
-    http::{request::Parts, Method, StatusCode},
-    response::{IntoResponse, Redirect, Response},
-    routing::{delete, get, post, put},
-    Json, RequestPartsExt, Router,
+    extract::{FromRequestParts, Path, Query, Request, State},
+    http::{header, request::Parts, Method, StatusCode},
+    middleware::{self, Next},
+    response::{AppendHeaders, IntoResponse, Redirect, Response},
+    routing::{delete, get, patch, post, put},
+    Extension, Json, RequestPartsExt, Router,
 };
 use axum_extra::{
-    headers::{authorization::Bearer, Authorization},
+    headers::{authorization::Bearer, Authorization, ETag, IfMatch, IfNoneMatch},
     TypedHeader,
 };
 use chrono::{prelude::*, TimeDelta};
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 extern crate jsonwebtoken;
-use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use serde::{de::DeserializeOwned, ser::SerializeStruct, Deserialize, Serialize, Serializer};
 extern crate serde_json;
 use sqlx::{
     self,
@@ -42,15 +85,18 @@ use sqlx::{
     Pool, Row,
 };
 extern crate thiserror;
-use tokio::{self, sync::RwLock};
-use tower_http::{services, trace, cors};
+use tokio::{
+    self,
+    sync::{mpsc, RwLock},
+};
+use tower_http::{cors, services, trace};
 use tower_sessions::{Expiry, MemoryStore, Session, SessionManagerLayer};
 extern crate tracing;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use utoipa::{
-    openapi::schema::{ObjectBuilder, Schema, SchemaType},
+    openapi::schema::{ArrayBuilder, ObjectBuilder, Ref, Schema, SchemaFormat, SchemaType},
     openapi::RefOr,
-    OpenApi, ToSchema,
+    IntoParams, OpenApi, ToSchema,
 };
 use utoipa_rapidoc::RapiDoc;
 use utoipa_redoc::{Redoc, Servable};
@@ -63,10 +109,53 @@ const STYLESHEET: &str = "assets/static/knock-knock.css";
 struct Args {
     #[clap(short, long, default_value = "0.0.0.0:3000")]
     serve: String,
+    /// Days a deleted joke stays in the trash before it is purged.
+    #[clap(
+        long,
+        env = "TRASH_RETENTION_DAYS",
+        default_value = "30",
+        value_parser = clap::value_parser!(i64).range(0..=MAX_RETENTION_DAYS),
+    )]
+    trash_retention_days: i64,
+    /// What to do with new jokes that look like copies of ones
+    /// already in the jokebase.
+    #[clap(
+        long,
+        value_enum,
+        global = true,
+        env = "DUPLICATE_POLICY",
+        default_value = "warn"
+    )]
+    duplicates: DuplicatePolicy,
+    /// Only tell and list all-ages jokes not waiting for review,
+    /// whatever the request asks for.
+    #[clap(long, env = "FAMILY_SAFE")]
+    family_safe: bool,
+    /// Run a maintenance command instead of serving.
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
-    startup(args.serve).await
+    match args.command {
+        None => {
+            let trash_retention = TimeDelta::try_days(args.trash_retention_days)
+                .expect("retention days checked by clap");
+            startup(
+                args.serve,
+                trash_retention,
+                args.duplicates,
+                args.family_safe,
+            )
+            .await
+        }
+        Some(command) => {
+            if let Err(e) = run_command(command, args.duplicates).await {
+                eprintln!("knock-knock: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
 }
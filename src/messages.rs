@@ -0,0 +1,422 @@
+//! Message catalogs for the web pages: the text of the
+//! templates and of the errors shown on them, in each locale.
+//! Messages may have `{}` placeholders, filled in order.
+
+use crate::*;
+
+/// A language the web pages are shown in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+}
+
+pub const LOCALES: [Locale; 2] = [Locale::En, Locale::Es];
+
+type Catalog = [(&'static str, &'static str)];
+
+const EN: &Catalog = &[
+    ("page.title", "Knock-Knock"),
+    ("index.from_collection", "From"),
+    ("index.groan_meter", "Groan meter:"),
+    ("index.also_in", "Also in:"),
+    ("index.related", "You might also like:"),
+    ("index.similar", "This joke looks like:"),
+    ("index.tell_another", "Tell me another!"),
+    ("index.ask_for_one", "Ask me for one!"),
+    ("index.find_joke", "Find this joke"),
+    ("index.language", "Language:"),
+    ("tell.title", "Submit A Knock-Knock Joke"),
+    ("tell.id", "Joke Id (optional):"),
+    ("tell.whos_there", "Who's There:"),
+    ("tell.answer_who", "Answer Who:"),
+    ("tell.tags", "Tags:"),
+    ("tell.source", "Source:"),
+    ("tell.submit", "Add this joke"),
+    ("error.no_such_joke", "cannot find joke {}"),
+    ("error.no_such_collection", "cannot find collection {}"),
+    ("error.empty_collection", "no jokes in this collection yet"),
+    ("error.no_jokes", "no jokes yet: ask me for one!"),
+    ("error.duplicate", "that joke looks like joke {}"),
+    ("error.joke_exists", "joke {} already exists"),
+    ("error.joke_invalid", "that joke has problems: {}"),
+    ("error.joke_unreadable", "that joke could not be read: {}"),
+    ("error.joke_renamed", "that joke is now joke {}"),
+    (
+        "error.joke_changed",
+        "joke {} changed while you were looking at it",
+    ),
+    ("error.no_such_revision", "joke {} has no revision {}"),
+    ("error.collection_exists", "collection {} already exists"),
+    (
+        "error.collection_invalid",
+        "that collection has problems: {}",
+    ),
+    ("error.tag_exists", "tag {} is already in use"),
+    ("error.no_such_tag", "cannot find tag {}"),
+    ("error.tag_invalid", "that tag has problems: {}"),
+    ("error.param_invalid", "that request has problems: {}"),
+    ("error.dump_invalid", "that dump has problems: {}"),
+    (
+        "error.internal",
+        "something went wrong here: please try again",
+    ),
+    (
+        "error.unavailable",
+        "the jokebase is away: please try again soon",
+    ),
+    ("field.empty", "must not be empty"),
+    ("field.too_long", "must be at most {} characters"),
+    (
+        "field.not_slug",
+        "must be lowercase letters and digits, separated by single hyphens",
+    ),
+    ("field.range", "must be from {} to {}"),
+    ("field.too_far_back", "is too far back"),
+    ("field.too_many_tags", "at most {} tags are allowed"),
+    (
+        "field.tag_too_long",
+        "tag \"{}\" is longer than {} characters",
+    ),
+    ("field.tag_comma", "tag \"{}\" contains a comma"),
+    (
+        "field.url_scheme",
+        "URL must start with http:// or https://",
+    ),
+    ("field.url_spaces", "URL must not contain spaces"),
+    ("field.url_host", "URL must name a host"),
+    ("field.self_translation", "must not be the joke itself"),
+    ("field.not_a_joke", "must be the id of a joke"),
+    (
+        "field.has_translations",
+        "must be empty for a joke that has translations",
+    ),
+    ("field.one_of", "must be one of {}"),
+    ("card.rating", "groans={}/5 from {} votes"),
+    ("card.rating_one", "groans={}/5 from 1 vote"),
+];
+
+const ES: &Catalog = &[
+    ("page.title", "Toc, toc"),
+    ("index.from_collection", "De"),
+    ("index.groan_meter", "Quejómetro:"),
+    ("index.also_in", "También en:"),
+    ("index.related", "También te puede gustar:"),
+    ("index.similar", "Este chiste se parece a:"),
+    ("index.tell_another", "¡Cuéntame otro!"),
+    ("index.ask_for_one", "¡Envíame uno!"),
+    ("index.find_joke", "Buscar este chiste"),
+    ("index.language", "Idioma:"),
+    ("tell.title", "Envía un chiste de toc, toc"),
+    ("tell.id", "Id del chiste (opcional):"),
+    ("tell.whos_there", "Quién es:"),
+    ("tell.answer_who", "Respuesta:"),
+    ("tell.tags", "Etiquetas:"),
+    ("tell.source", "Fuente:"),
+    ("tell.submit", "Añadir este chiste"),
+    ("error.no_such_joke", "no se encuentra el chiste {}"),
+    (
+        "error.no_such_collection",
+        "no se encuentra la colección {}",
+    ),
+    (
+        "error.empty_collection",
+        "todavía no hay chistes en esta colección",
+    ),
+    ("error.no_jokes", "todavía no hay chistes: ¡envíame uno!"),
+    ("error.duplicate", "ese chiste se parece al chiste {}"),
+    ("error.joke_exists", "el chiste {} ya existe"),
+    ("error.joke_invalid", "ese chiste tiene problemas: {}"),
+    ("error.joke_unreadable", "no se pudo leer ese chiste: {}"),
+    ("error.joke_renamed", "ese chiste ahora es el chiste {}"),
+    (
+        "error.joke_changed",
+        "el chiste {} cambió mientras lo mirabas",
+    ),
+    (
+        "error.no_such_revision",
+        "el chiste {} no tiene la revisión {}",
+    ),
+    ("error.collection_exists", "la colección {} ya existe"),
+    (
+        "error.collection_invalid",
+        "esa colección tiene problemas: {}",
+    ),
+    ("error.tag_exists", "la etiqueta {} ya está en uso"),
+    ("error.no_such_tag", "no se encuentra la etiqueta {}"),
+    ("error.tag_invalid", "esa etiqueta tiene problemas: {}"),
+    ("error.param_invalid", "esa petición tiene problemas: {}"),
+    ("error.dump_invalid", "esa copia tiene problemas: {}"),
+    ("error.internal", "algo salió mal aquí: inténtalo de nuevo"),
+    (
+        "error.unavailable",
+        "la base de chistes no está: inténtalo de nuevo pronto",
+    ),
+    ("field.empty", "no puede estar vacío"),
+    ("field.too_long", "debe tener como máximo {} caracteres"),
+    (
+        "field.not_slug",
+        "deben ser letras minúsculas y dígitos, separados por guiones simples",
+    ),
+    ("field.range", "debe estar entre {} y {}"),
+    ("field.too_far_back", "queda demasiado atrás"),
+    (
+        "field.too_many_tags",
+        "se permiten como máximo {} etiquetas",
+    ),
+    (
+        "field.tag_too_long",
+        "la etiqueta \"{}\" tiene más de {} caracteres",
+    ),
+    ("field.tag_comma", "la etiqueta \"{}\" contiene una coma"),
+    (
+        "field.url_scheme",
+        "la URL debe empezar por http:// o https://",
+    ),
+    ("field.url_spaces", "la URL no puede contener espacios"),
+    ("field.url_host", "la URL debe nombrar un servidor"),
+    ("field.self_translation", "no puede ser el propio chiste"),
+    ("field.not_a_joke", "debe ser el id de un chiste"),
+    (
+        "field.has_translations",
+        "debe estar vacío en un chiste que tiene traducciones",
+    ),
+    ("field.one_of", "debe ser uno de {}"),
+    ("card.rating", "quejidos={}/5 de {} votos"),
+    ("card.rating_one", "quejidos={}/5 de 1 voto"),
+];
+
+impl Locale {
+    /// The first of `langs` there is a catalog for, or English.
+    pub fn choose(langs: &[String]) -> Self {
+        langs
+            .iter()
+            .find_map(|lang| LOCALES.into_iter().find(|locale| locale.code() == lang))
+            .unwrap_or_default()
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Es => "es",
+        }
+    }
+
+    /// Name of the locale in its own language.
+    pub fn name(self) -> &'static str {
+        match self {
+            Locale::En => "English",
+            Locale::Es => "Español",
+        }
+    }
+
+    fn catalog(self) -> &'static Catalog {
+        match self {
+            Locale::En => EN,
+            Locale::Es => ES,
+        }
+    }
+
+    /// The message with the given key, in English if it has
+    /// not been translated.
+    pub fn t(self, key: &str) -> &str {
+        let lookup = |catalog: &'static Catalog| {
+            catalog
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, message)| *message)
+        };
+        lookup(self.catalog()).or_else(|| lookup(EN)).unwrap_or(key)
+    }
+
+    /// The message with its placeholders filled in.
+    pub fn fill(self, key: &str, args: &[&str]) -> String {
+        let mut parts = self.t(key).split("{}");
+        let mut message = parts.next().unwrap_or_default().to_string();
+        for (part, arg) in parts.zip(args.iter().chain(std::iter::repeat(&""))) {
+            message += arg;
+            message += part;
+        }
+        message
+    }
+
+    /// Message for a web page about a jokebase error.
+    pub fn error(self, error: &JokeBaseErr) -> String {
+        match error {
+            JokeBaseErr::JokeExists(id) => self.fill("error.joke_exists", &[id]),
+            JokeBaseErr::JokeBaseIoError(_) | JokeBaseErr::DatabaseError(_) => {
+                self.t("error.internal").to_string()
+            }
+            JokeBaseErr::DatabaseUnavailable(_) => self.t("error.unavailable").to_string(),
+            JokeBaseErr::NoJoke => self.t("error.no_jokes").to_string(),
+            JokeBaseErr::JokeDoesNotExist(id) => self.fill("error.no_such_joke", &[id]),
+            JokeBaseErr::JokeUnprocessable(reason) => self.fill("error.joke_unreadable", &[reason]),
+            JokeBaseErr::JokeInvalid(errors) => {
+                self.fill("error.joke_invalid", &[&self.field_errors(errors)])
+            }
+            JokeBaseErr::JokeRenamed(id) => self.fill("error.joke_renamed", &[id]),
+            JokeBaseErr::JokeChanged(id) => self.fill("error.joke_changed", &[id]),
+            JokeBaseErr::RevisionDoesNotExist(id, revision) => {
+                self.fill("error.no_such_revision", &[id, &revision.to_string()])
+            }
+            JokeBaseErr::DuplicateJoke(id) => self.fill("error.duplicate", &[id]),
+            JokeBaseErr::CollectionExists(id) => self.fill("error.collection_exists", &[id]),
+            JokeBaseErr::CollectionDoesNotExist(id) => self.fill("error.no_such_collection", &[id]),
+            JokeBaseErr::CollectionInvalid(errors) => {
+                self.fill("error.collection_invalid", &[&self.field_errors(errors)])
+            }
+            JokeBaseErr::TagExists(tag) => self.fill("error.tag_exists", &[tag]),
+            JokeBaseErr::TagDoesNotExist(tag) => self.fill("error.no_such_tag", &[tag]),
+            JokeBaseErr::TagInvalid(errors) => {
+                self.fill("error.tag_invalid", &[&self.field_errors(errors)])
+            }
+            JokeBaseErr::ParamInvalid(errors) => {
+                self.fill("error.param_invalid", &[&self.field_errors(errors)])
+            }
+            JokeBaseErr::DumpInvalid(errors) => {
+                self.fill("error.dump_invalid", &[&self.field_errors(errors)])
+            }
+        }
+    }
+
+    /// Message of a field error. Messages only the API gives
+    /// are not in the catalogs, and stay in English.
+    pub fn field_message(self, error: &FieldError) -> String {
+        match &error.key {
+            Some((key, args)) => {
+                let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                self.fill(key, &args)
+            }
+            None => error.message.clone(),
+        }
+    }
+
+    fn field_errors(self, errors: &[FieldError]) -> String {
+        let errors: Vec<String> = errors
+            .iter()
+            .map(|e| format!("{}: {}", e.field, self.field_message(e)))
+            .collect();
+        errors.join("; ")
+    }
+
+    /// A joke's groan meter reading, for its card.
+    pub fn rating(self, rating: &Rating) -> String {
+        let average = format!("{:.1}", rating.average);
+        if rating.votes == 1 {
+            self.fill("card.rating_one", &[&average])
+        } else {
+            self.fill("card.rating", &[&average, &rating.votes.to_string()])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(catalog: &Catalog) -> Vec<&str> {
+        catalog.iter().map(|(key, _)| *key).collect()
+    }
+
+    #[test]
+    fn every_locale_has_every_key() {
+        for locale in LOCALES {
+            let mut catalog = keys(locale.catalog());
+            catalog.sort();
+            let mut english = keys(EN);
+            english.sort();
+            assert_eq!(catalog, english, "keys of {}", locale.code());
+            catalog.dedup();
+            assert_eq!(
+                catalog.len(),
+                EN.len(),
+                "repeated keys in {}",
+                locale.code()
+            );
+        }
+    }
+
+    #[test]
+    fn translations_keep_placeholders() {
+        for locale in LOCALES {
+            for (key, message) in EN {
+                assert_eq!(
+                    locale.t(key).matches("{}").count(),
+                    message.matches("{}").count(),
+                    "placeholders of {} in {}",
+                    key,
+                    locale.code(),
+                );
+            }
+        }
+    }
+
+    /// Keys given as string literals to `t` or `fill`, and field
+    /// error keys, in `source`.
+    fn used_keys(source: &str) -> Vec<&str> {
+        let calls = [".t(\"", ".fill(\""].iter().flat_map(|call| {
+            source
+                .match_indices(call)
+                .map(move |(i, _)| &source[i + call.len()..])
+        });
+        // Pieced together so as not to match itself.
+        let fields = source
+            .match_indices(concat!('"', "field", '.'))
+            .map(|(i, _)| &source[i + 1..]);
+        calls
+            .chain(fields)
+            .filter_map(|rest| rest.split('"').next())
+            .collect()
+    }
+
+    #[test]
+    fn used_keys_exist() {
+        let sources = [
+            include_str!("../assets/templates/index.html"),
+            include_str!("../assets/templates/tell.html"),
+            include_str!("../assets/templates/joke-card.html"),
+            include_str!("web.rs"),
+            include_str!("messages.rs"),
+            include_str!("validate.rs"),
+            include_str!("ratings.rs"),
+            include_str!("lang.rs"),
+            include_str!("tags.rs"),
+        ];
+        let english = keys(EN);
+        for source in sources {
+            for key in used_keys(source) {
+                assert!(english.contains(&key), "no message {}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn fill_placeholders() {
+        assert_eq!(
+            Locale::Es.fill("error.joke_exists", &["boo"]),
+            "el chiste boo ya existe"
+        );
+        assert_eq!(
+            Locale::choose(&["fr".to_string(), "es".to_string()]),
+            Locale::Es
+        );
+        assert_eq!(Locale::choose(&[]), Locale::En);
+    }
+
+    #[test]
+    fn field_errors_in_locale() {
+        let error = check_text("whos_there", "", MAX_WHOS_THERE_LEN).unwrap();
+        assert_eq!(error.message, "must not be empty");
+        assert_eq!(Locale::Es.field_message(&error), "no puede estar vacío");
+        let error = check_id(&"a".repeat(MAX_ID_LEN + 1)).unwrap();
+        assert_eq!(
+            Locale::Es.field_message(&error),
+            "debe tener como máximo 64 caracteres"
+        );
+        let error = JokeBaseErr::JokeInvalid(vec![check_lang("xx").unwrap()]);
+        assert!(Locale::Es
+            .error(&error)
+            .starts_with("ese chiste tiene problemas: lang: debe ser uno de"));
+    }
+}
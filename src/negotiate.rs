@@ -0,0 +1,300 @@
+//! Choice of joke representation from the request's `Accept`
+//! header.
+
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JokeFormat {
+    Json,
+    Text,
+    Html,
+    Markdown,
+    Yaml,
+    Csv,
+}
+
+/// Request headers the format is chosen by.
+pub const FORMAT_VARY: &str = "Accept, User-Agent";
+
+/// Columns of the CSV representation.
+const CSV_COLUMNS: [&str; 9] = [
+    "id",
+    "whos_there",
+    "answer_who",
+    "tags",
+    "source",
+    "script",
+    "lang",
+    "translation_of",
+    "audience",
+];
+
+impl JokeFormat {
+    pub const ALL: [JokeFormat; 6] = [
+        JokeFormat::Json,
+        JokeFormat::Text,
+        JokeFormat::Html,
+        JokeFormat::Markdown,
+        JokeFormat::Yaml,
+        JokeFormat::Csv,
+    ];
+
+    fn from_media_range(range: &str) -> Option<Self> {
+        match range {
+            "application/json" | "application/*" => Some(JokeFormat::Json),
+            "text/plain" | "text/*" => Some(JokeFormat::Text),
+            "text/html" => Some(JokeFormat::Html),
+            "text/markdown" | "text/x-markdown" => Some(JokeFormat::Markdown),
+            "application/yaml" | "application/x-yaml" | "text/yaml" => Some(JokeFormat::Yaml),
+            "text/csv" => Some(JokeFormat::Csv),
+            _ => None,
+        }
+    }
+
+    /// Format for clients that will take anything: JSON,
+    /// except for command-line tools, which get plain text.
+    /// Responses name `User-Agent` in `Vary` for this.
+    fn default_for(user_agent: Option<&str>) -> Self {
+        match user_agent {
+            Some(ua) if ua.starts_with("curl/") || ua.starts_with("Wget/") => JokeFormat::Text,
+            _ => JokeFormat::Json,
+        }
+    }
+
+    /// Best format acceptable to the client, or `None` if it
+    /// accepts none of them.
+    pub fn negotiate(accept: Option<&str>, user_agent: Option<&str>) -> Option<Self> {
+        let Some(accept) = accept else {
+            return Some(Self::default_for(user_agent));
+        };
+        let mut ranges: Vec<(&str, f32)> = accept
+            .split(',')
+            .filter_map(|range| {
+                let mut params = range.split(';').map(str::trim);
+                let media = params.next()?;
+                let q = params
+                    .find_map(|p| p.strip_prefix("q="))
+                    .and_then(|q| q.parse().ok())
+                    .unwrap_or(1.0);
+                Some((media, q))
+            })
+            .filter(|&(_, q)| q > 0.0)
+            .collect();
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges.into_iter().find_map(|(media, _)| match media {
+            "*/*" => Some(Self::default_for(user_agent)),
+            media => Self::from_media_range(&media.to_ascii_lowercase()),
+        })
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            JokeFormat::Json => "application/json",
+            JokeFormat::Text => "text/plain; charset=utf-8",
+            JokeFormat::Html => "text/html; charset=utf-8",
+            JokeFormat::Markdown => "text/markdown; charset=utf-8",
+            JokeFormat::Yaml => "application/yaml",
+            JokeFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn render_joke(self, joke: &Joke) -> String {
+        match self {
+            JokeFormat::Json => serde_json::to_string(joke).unwrap(),
+            JokeFormat::Yaml => yaml_lines(&serde_json::Value::Object(joke.canonical_json()))
+                .into_iter()
+                .map(|line| line + "\n")
+                .collect(),
+            format => format.render_jokes(std::slice::from_ref(joke)),
+        }
+    }
+
+    pub fn render_jokes(self, jokes: &[Joke]) -> String {
+        match self {
+            JokeFormat::Json => serde_json::to_string(jokes).unwrap(),
+            JokeFormat::Text => jokes
+                .iter()
+                .map(String::from)
+                .collect::<Vec<String>>()
+                .join("\n"),
+            JokeFormat::Html => JokesTemplate::new(jokes).render().unwrap(),
+            JokeFormat::Markdown => jokes
+                .iter()
+                .map(joke_markdown)
+                .collect::<Vec<String>>()
+                .join("\n---\n\n"),
+            JokeFormat::Yaml => {
+                let jokes = jokes
+                    .iter()
+                    .map(|joke| serde_json::Value::Object(joke.canonical_json()))
+                    .collect();
+                yaml_lines(&serde_json::Value::Array(jokes))
+                    .into_iter()
+                    .map(|line| line + "\n")
+                    .collect()
+            }
+            JokeFormat::Csv => {
+                let mut csv = CSV_COLUMNS.join(",") + "\r\n";
+                for joke in jokes {
+                    csv += &joke_csv(joke);
+                }
+                csv
+            }
+        }
+    }
+
+    fn headers(self) -> [(header::HeaderName, &'static str); 2] {
+        [
+            (header::CONTENT_TYPE, self.content_type()),
+            (header::VARY, FORMAT_VARY),
+        ]
+    }
+
+    pub fn joke_response(self, joke: &Joke) -> Response {
+        (self.headers(), self.render_joke(joke)).into_response()
+    }
+
+    pub fn jokes_response(self, jokes: &[Joke]) -> Response {
+        (self.headers(), self.render_jokes(jokes)).into_response()
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for JokeFormat {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = |name| parts.headers.get(name).and_then(|v| v.to_str().ok());
+        JokeFormat::negotiate(header(header::ACCEPT), header(header::USER_AGENT)).ok_or_else(|| {
+            let accepted = "application/json, text/plain, text/html, text/markdown, \
+                            application/yaml, text/csv";
+            let detail = format!("jokes are available as: {}", accepted);
+            Problem::new(
+                StatusCode::NOT_ACCEPTABLE,
+                "not-acceptable",
+                "No acceptable format",
+                detail,
+            )
+            .into_response()
+        })
+    }
+}
+
+fn markdown_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_[]<>#|".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn joke_markdown(joke: &Joke) -> String {
+    let told: Vec<String> = joke
+        .told()
+        .iter()
+        .map(|turn| {
+            let line = markdown_escape(&turn.line);
+            match turn.speaker {
+                Speaker::Teller => format!("**{}**", line),
+                Speaker::Tellee => format!("*{}*", line),
+                Speaker::Direction => line,
+            }
+        })
+        .collect();
+    let mut text = told.join("  \n") + "\n\n";
+
+    let mut annote: Vec<String> = vec![format!("id: `{}`", joke.id)];
+    if let Some(tags) = &joke.tags {
+        annote.push(format!("tags: {}", markdown_escape(&format_tags(tags))));
+    }
+    if joke.lang != DEFAULT_LANG {
+        annote.push(format!("lang: {}", joke.lang));
+    }
+    if joke.audience != Audience::AllAges {
+        annote.push(format!("audience: {}", joke.audience.as_str()));
+    }
+    if let Some(source) = &joke.source {
+        annote.push(format!("source: <{}>", source));
+    }
+    text += &format!("_{}_\n", annote.join("; "));
+    text
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn joke_csv(joke: &Joke) -> String {
+    let mut tags: Vec<&str> = joke.tags.iter().flatten().map(String::as_str).collect();
+    tags.sort();
+    // A script is written one "speaker: line" per line.
+    let script: Vec<String> = joke
+        .script
+        .iter()
+        .flatten()
+        .map(|turn| format!("{}: {}", turn.speaker.as_str(), turn.line))
+        .collect();
+    let fields = [
+        joke.id.as_str(),
+        &joke.whos_there,
+        &joke.answer_who,
+        &tags.join(";"),
+        joke.source.as_deref().unwrap_or(""),
+        &script.join("\n"),
+        &joke.lang,
+        joke.translation_of.as_deref().unwrap_or(""),
+        joke.audience.as_str(),
+    ];
+    let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+    fields.join(",") + "\r\n"
+}
+
+fn yaml_scalar(value: &serde_json::Value) -> String {
+    // JSON scalars, including double-quoted strings, are valid YAML.
+    value.to_string()
+}
+
+fn yaml_lines(value: &serde_json::Value) -> Vec<String> {
+    use serde_json::Value;
+
+    match value {
+        Value::Object(fields) if !fields.is_empty() => fields
+            .iter()
+            .flat_map(|(key, value)| match value {
+                Value::Object(f) if !f.is_empty() => nested_yaml(key, value),
+                Value::Array(a) if !a.is_empty() => nested_yaml(key, value),
+                scalar => vec![format!("{}: {}", key, yaml_scalar(scalar))],
+            })
+            .collect(),
+        Value::Array(items) if !items.is_empty() => items
+            .iter()
+            .flat_map(|item| {
+                yaml_lines(item).into_iter().enumerate().map(|(i, line)| {
+                    if i == 0 {
+                        format!("- {}", line)
+                    } else {
+                        format!("  {}", line)
+                    }
+                })
+            })
+            .collect(),
+        scalar => vec![yaml_scalar(scalar)],
+    }
+}
+
+fn nested_yaml(key: &str, value: &serde_json::Value) -> Vec<String> {
+    std::iter::once(format!("{}:", key))
+        .chain(
+            yaml_lines(value)
+                .into_iter()
+                .map(|line| format!("  {}", line)),
+        )
+        .collect()
+}
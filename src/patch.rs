@@ -0,0 +1,52 @@
+use crate::*;
+
+/// Tag changes to a joke that leave its other tags alone.
+/// Removals are applied before additions.
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct TagEdit {
+    #[schema(example = r#"["animal"]"#)]
+    #[serde(default)]
+    pub add: HashSet<String>,
+    #[schema(example = r#"["food"]"#)]
+    #[serde(default)]
+    pub remove: HashSet<String>,
+}
+
+/// Apply an RFC 7396 JSON Merge Patch to `target`.
+pub fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    use serde_json::Value;
+
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(serde_json::Map::new());
+    }
+    let Value::Object(fields) = target else {
+        unreachable!();
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            fields.remove(key);
+        } else {
+            merge_patch(fields.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// The joke that results from applying a merge patch to it.
+/// The patch may not change the joke's id.
+pub fn apply_joke_patch(joke: &Joke, patch: &serde_json::Value) -> Result<Joke, JokeBaseErr> {
+    let mut value = serde_json::Value::Object(joke.canonical_json());
+    merge_patch(&mut value, patch);
+    let patched: Joke =
+        serde_json::from_value(value).map_err(|e| JokeBaseErr::JokeUnprocessable(e.to_string()))?;
+    if patched.id != joke.id {
+        return Err(JokeBaseErr::JokeUnprocessable(format!(
+            "cannot change id of joke {} with a patch",
+            joke.id,
+        )));
+    }
+    Ok(patched)
+}
@@ -0,0 +1,346 @@
+//! Error responses as RFC 7807 `application/problem+json`.
+
+use crate::*;
+
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// Problem type URIs are this followed by a short name for
+/// the kind of problem. They are identifiers only, and need
+/// not resolve.
+pub const PROBLEM_TYPE_BASE: &str = "https://knock-knock.po8.org/problems/";
+
+#[derive(Debug)]
+pub struct Problem {
+    /// Short name of the problem type, as in `joke-exists`.
+    pub kind: &'static str,
+    pub title: &'static str,
+    pub status: StatusCode,
+    pub detail: String,
+    /// The joke the problem is about, if any.
+    pub joke_id: Option<String>,
+    /// Problems with particular fields of a submitted joke.
+    pub errors: Vec<FieldError>,
+}
+
+impl Problem {
+    pub fn new(
+        status: StatusCode,
+        kind: &'static str,
+        title: &'static str,
+        detail: String,
+    ) -> Self {
+        Problem {
+            kind,
+            title,
+            status,
+            detail,
+            joke_id: None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn type_uri(&self) -> String {
+        format!("{}{}", PROBLEM_TYPE_BASE, self.kind)
+    }
+
+    /// Response reporting a jokebase error. A renamed joke
+    /// also gets a `Location` for its new id, which
+    /// `redirect_renamed` points at the resource asked for.
+    pub fn response(error: JokeBaseErr) -> Response {
+        let problem = Problem::from(&error).into_response();
+        match error {
+            JokeBaseErr::JokeRenamed(id) => {
+                let location = format!("/api/v1/joke/{}", id);
+                (
+                    [(header::LOCATION, location)],
+                    Extension(Renamed(id)),
+                    problem,
+                )
+                    .into_response()
+            }
+            _ => problem,
+        }
+    }
+}
+
+/// New id of a renamed joke, kept on the response reporting it.
+#[derive(Debug, Clone)]
+struct Renamed(String);
+
+/// The request path and query, with the joke id after `joke/`
+/// replaced by `id`: where a request naming a renamed joke by its
+/// old id should go, sub-resource and all.
+pub fn renamed_location(path: &str, query: Option<&str>, id: &str) -> String {
+    let mut segments: Vec<&str> = path.split('/').collect();
+    match segments.iter().position(|&segment| segment == "joke") {
+        Some(i) if i + 1 < segments.len() => segments[i + 1] = id,
+        _ => return format!("/api/v1/joke/{}", id),
+    }
+    let path = segments.join("/");
+    match query {
+        Some(query) => format!("{}?{}", path, query),
+        None => path,
+    }
+}
+
+/// Middleware pointing the `Location` of a renamed joke's
+/// redirect at the resource the request asked for under the
+/// joke's new id.
+pub async fn redirect_renamed(request: Request, next: Next) -> Response {
+    let uri = request.uri().clone();
+    let mut response = next.run(request).await;
+    if let Some(Renamed(id)) = response.extensions_mut().remove::<Renamed>() {
+        let location = renamed_location(uri.path(), uri.query(), &id);
+        if let Ok(location) = location.parse() {
+            response.headers_mut().insert(header::LOCATION, location);
+        }
+    }
+    response
+}
+
+impl From<&JokeBaseErr> for Problem {
+    fn from(error: &JokeBaseErr) -> Self {
+        let (kind, title) = error.problem_type();
+        let errors = match error {
+            JokeBaseErr::JokeInvalid(errors)
+            | JokeBaseErr::CollectionInvalid(errors)
+            | JokeBaseErr::TagInvalid(errors)
+            | JokeBaseErr::ParamInvalid(errors)
+            | JokeBaseErr::DumpInvalid(errors) => errors.clone(),
+            _ => Vec::new(),
+        };
+        Problem {
+            joke_id: error.joke_id().map(str::to_string),
+            errors,
+            ..Problem::new(error.status(), kind, title, error.to_string())
+        }
+    }
+}
+
+impl Serialize for Problem {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let nfields =
+            4 + usize::from(self.joke_id.is_some()) + usize::from(!self.errors.is_empty());
+        let mut state = serializer.serialize_struct("Problem", nfields)?;
+        state.serialize_field("type", &self.type_uri())?;
+        state.serialize_field("title", self.title)?;
+        state.serialize_field("status", &self.status.as_u16())?;
+        state.serialize_field("detail", &self.detail)?;
+        if let Some(joke_id) = &self.joke_id {
+            state.serialize_field("joke_id", joke_id)?;
+        }
+        if !self.errors.is_empty() {
+            state.serialize_field("errors", &self.errors)?;
+        }
+        state.end()
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let body = serde_json::to_string(&self).unwrap();
+        (
+            self.status,
+            [(header::CONTENT_TYPE, PROBLEM_CONTENT_TYPE)],
+            body,
+        )
+            .into_response()
+    }
+}
+
+pub fn error_schema(name: &str, example: serde_json::Value) -> (&str, RefOr<Schema>) {
+    let string = || ObjectBuilder::new().schema_type(SchemaType::String);
+    let sch = ObjectBuilder::new()
+        .property(
+            "type",
+            string().format(Some(SchemaFormat::Custom("uri".into()))),
+        )
+        .required("type")
+        .property("title", string())
+        .required("title")
+        .property(
+            "status",
+            ObjectBuilder::new().schema_type(SchemaType::Integer),
+        )
+        .required("status")
+        .property("detail", string())
+        .property("joke_id", string())
+        .property(
+            "errors",
+            ArrayBuilder::new().items(Ref::from_schema_name("FieldError")),
+        )
+        .example(Some(example))
+        .into();
+    (name, sch)
+}
+
+impl<'s> ToSchema<'s> for Problem {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        let example = serde_json::json!({
+            "type": format!("{}joke-does-not-exist", PROBLEM_TYPE_BASE),
+            "title": "Joke does not exist",
+            "status": 404,
+            "detail": "joke boo doesn't exist",
+            "joke_id": "boo",
+        });
+        error_schema("Problem", example)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(error: &JokeBaseErr) -> serde_json::Value {
+        serde_json::to_value(Problem::from(error)).unwrap()
+    }
+
+    #[test]
+    fn missing_jokes_are_not_found() {
+        for error in [
+            JokeBaseErr::NoJoke,
+            JokeBaseErr::JokeDoesNotExist("boo".to_string()),
+            JokeBaseErr::RevisionDoesNotExist("boo".to_string(), 3),
+        ] {
+            assert_eq!(error.status(), StatusCode::NOT_FOUND);
+            assert_eq!(Problem::response(error).status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[test]
+    fn client_errors() {
+        let cases = [
+            (
+                JokeBaseErr::JokeExists("boo".to_string()),
+                StatusCode::CONFLICT,
+                "joke-exists",
+            ),
+            (
+                JokeBaseErr::JokeUnprocessable("no answer".to_string()),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "joke-unprocessable",
+            ),
+            (
+                JokeBaseErr::JokeChanged("boo".to_string()),
+                StatusCode::PRECONDITION_FAILED,
+                "joke-changed",
+            ),
+            (
+                JokeBaseErr::JokeInvalid(vec![check_id("Boo").unwrap()]),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "joke-invalid",
+            ),
+            (
+                JokeBaseErr::DuplicateJoke("boo".to_string()),
+                StatusCode::CONFLICT,
+                "duplicate-joke",
+            ),
+            (
+                JokeBaseErr::CollectionExists("halloween".to_string()),
+                StatusCode::CONFLICT,
+                "collection-exists",
+            ),
+            (
+                JokeBaseErr::CollectionDoesNotExist("halloween".to_string()),
+                StatusCode::NOT_FOUND,
+                "collection-does-not-exist",
+            ),
+            (
+                JokeBaseErr::CollectionInvalid(vec![FieldError::new("name", "must not be empty")]),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "collection-invalid",
+            ),
+            (
+                JokeBaseErr::TagExists("animal".to_string()),
+                StatusCode::CONFLICT,
+                "tag-exists",
+            ),
+            (
+                JokeBaseErr::TagDoesNotExist("animal".to_string()),
+                StatusCode::NOT_FOUND,
+                "tag-does-not-exist",
+            ),
+            (
+                JokeBaseErr::TagInvalid(vec![FieldError::new("to", "must not be empty")]),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "tag-invalid",
+            ),
+            (
+                days_ago("days", -1, 30).unwrap_err(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "param-invalid",
+            ),
+            (
+                JokeBaseErr::DumpInvalid(vec![FieldError::new("[1].id", "boo is used twice")]),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "dump-invalid",
+            ),
+        ];
+        for (error, expected, kind) in cases {
+            assert_eq!(error.status(), expected);
+            let body = body(&error);
+            assert_eq!(body["status"], expected.as_u16());
+            assert_eq!(body["type"], format!("{}{}", PROBLEM_TYPE_BASE, kind));
+        }
+    }
+
+    #[test]
+    fn server_errors() {
+        let error = |e: &str| e.to_string();
+        for (error, expected) in [
+            (
+                JokeBaseErr::JokeBaseIoError(error("disk full")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                JokeBaseErr::DatabaseError(error("bad sql")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                JokeBaseErr::DatabaseUnavailable(error("timed out")),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+        ] {
+            assert_eq!(error.status(), expected);
+            assert!(body(&error).get("joke_id").is_none());
+        }
+    }
+
+    #[test]
+    fn renamed_joke_redirects() {
+        let response = Problem::response(JokeBaseErr::JokeRenamed("europe".to_string()));
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(response.headers()[header::LOCATION], "/api/v1/joke/europe");
+        let location =
+            renamed_location("/api/v1/joke/europe-not/related", Some("limit=3"), "europe");
+        assert_eq!(location, "/api/v1/joke/europe/related?limit=3");
+        let location = renamed_location("/api/v1/joke/europe-not", None, "europe");
+        assert_eq!(location, "/api/v1/joke/europe");
+    }
+
+    #[test]
+    fn problems_name_their_joke() {
+        let error = JokeBaseErr::JokeDoesNotExist("boo".to_string());
+        let body = body(&error);
+        assert_eq!(body["joke_id"], "boo");
+        assert_eq!(body["title"], "Joke does not exist");
+        let response = Problem::response(error);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            PROBLEM_CONTENT_TYPE
+        );
+    }
+
+    #[test]
+    fn database_errors() {
+        let status = |e: sqlx::Error| JokeBaseErr::from(e).status();
+        assert_eq!(
+            status(sqlx::Error::PoolTimedOut),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            status(sqlx::Error::RowNotFound),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
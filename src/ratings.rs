@@ -0,0 +1,88 @@
+//! The "groan meter": visitors rate jokes from one groan
+//! (a dud) to five (a joke that really lands).
+
+use crate::*;
+
+pub const MIN_RATING: i16 = 1;
+pub const MAX_RATING: i16 = 5;
+
+/// Weighted random choice treats every joke as having this many
+/// extra votes of `PRIOR_RATING`, so that a single vote doesn't
+/// make or break a joke.
+pub const PRIOR_VOTES: f64 = 2.0;
+pub const PRIOR_RATING: f64 = 3.0;
+
+const SESSION_VOTER_KEY: &str = "voter";
+
+/// How a joke has been rated so far.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Rating {
+    #[schema(example = 12)]
+    pub votes: i32,
+    /// Mean number of groans.
+    #[schema(example = 3.5)]
+    pub average: f64,
+}
+
+impl Rating {
+    /// Rating from the stored totals, if there are any votes.
+    pub fn from_totals(votes: i32, groans: i32) -> Option<Self> {
+        if votes == 0 {
+            return None;
+        }
+        Some(Rating {
+            votes,
+            average: f64::from(groans) / f64::from(votes),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct Vote {
+    /// Groans, from 1 to 5.
+    #[schema(example = 4, minimum = 1, maximum = 5)]
+    pub rating: i16,
+}
+
+pub fn check_rating(rating: i16) -> Result<(), JokeBaseErr> {
+    if (MIN_RATING..=MAX_RATING).contains(&rating) {
+        return Ok(());
+    }
+    let (min, max) = (MIN_RATING.to_string(), MAX_RATING.to_string());
+    let error = FieldError::from_key("rating", "field.range", &[&min, &max]);
+    Err(JokeBaseErr::JokeInvalid(vec![error]))
+}
+
+/// Voter for a registered user.
+pub fn user_voter(claims: &Claims) -> String {
+    format!("user:{}", claims.author())
+}
+
+/// Voter for an anonymous visitor, the same for the whole of
+/// their session.
+pub async fn session_voter(session: &Session) -> Result<String, JokeBaseErr> {
+    let store_error =
+        |e: tower_sessions::session::Error| JokeBaseErr::JokeBaseIoError(e.to_string());
+    if let Some(voter) = session
+        .get::<String>(SESSION_VOTER_KEY)
+        .await
+        .map_err(store_error)?
+    {
+        return Ok(voter);
+    }
+    // A session only gets an id once it has been saved.
+    session
+        .insert(SESSION_VOTER_KEY, "")
+        .await
+        .map_err(store_error)?;
+    session.save().await.map_err(store_error)?;
+    let id = session
+        .id()
+        .ok_or_else(|| JokeBaseErr::JokeBaseIoError("no session id".to_string()))?;
+    let voter = format!("session:{}", id);
+    session
+        .insert(SESSION_VOTER_KEY, &voter)
+        .await
+        .map_err(store_error)?;
+    Ok(voter)
+}
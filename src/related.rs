@@ -0,0 +1,84 @@
+//! "More like this": jokes ranked by how much they have in
+//! common with a given one.
+
+use crate::*;
+
+/// Score for tags shared, as a fraction of all tags of the two
+/// jokes.
+const TAG_WEIGHT: f64 = 2.0;
+/// Score for coming from the same source.
+const SOURCE_WEIGHT: f64 = 1.0;
+/// Score for words shared, as a fraction of all words of the two
+/// jokes.
+const TEXT_WEIGHT: f64 = 1.5;
+/// Words this short ("a", "the", "you") say nothing about what
+/// a joke is about.
+const MIN_WORD_LEN: usize = 4;
+
+/// A joke related to another, with how closely.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RelatedJoke {
+    #[serde(flatten)]
+    pub joke: Joke,
+    #[schema(example = 1.25)]
+    pub score: f64,
+}
+
+fn jaccard<T: Eq + std::hash::Hash>(a: &HashSet<T>, b: &HashSet<T>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+fn tag_set(joke: &Joke) -> HashSet<&str> {
+    joke.tags.iter().flatten().map(String::as_str).collect()
+}
+
+fn word_set(joke: &Joke) -> HashSet<String> {
+    let text = normalize_text(&format!("{} {}", joke.whos_there, joke.answer_who));
+    text.split_whitespace()
+        .filter(|word| word.chars().count() >= MIN_WORD_LEN)
+        .map(str::to_string)
+        .collect()
+}
+
+fn same_source(a: &Joke, b: &Joke) -> bool {
+    match (&a.source, &b.source) {
+        (Some(a), Some(b)) => a.trim().eq_ignore_ascii_case(b.trim()),
+        _ => false,
+    }
+}
+
+/// How much two jokes have in common: 0 for nothing.
+pub fn related_score(a: &Joke, b: &Joke) -> f64 {
+    let mut score = TAG_WEIGHT * jaccard(&tag_set(a), &tag_set(b));
+    if same_source(a, b) {
+        score += SOURCE_WEIGHT;
+    }
+    score + TEXT_WEIGHT * jaccard(&word_set(a), &word_set(b))
+}
+
+/// The jokes most related to `joke`, best first, leaving out
+/// the joke itself, its translations and copies of it.
+pub fn rank_related(joke: &Joke, candidates: Vec<Joke>, limit: usize) -> Vec<RelatedJoke> {
+    let mut related: Vec<RelatedJoke> = candidates
+        .into_iter()
+        .filter(|other| {
+            other.original_id() != joke.original_id() && !is_near_duplicate(joke, other)
+        })
+        .map(|other| RelatedJoke {
+            score: related_score(joke, &other),
+            joke: other,
+        })
+        .filter(|related| related.score > 0.0)
+        .collect();
+    related.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.joke.id.cmp(&b.joke.id))
+    });
+    related.truncate(limit);
+    related
+}
@@ -0,0 +1,103 @@
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RevisionAction {
+    Add,
+    Update,
+    Delete,
+    Restore,
+    Rename,
+}
+
+impl RevisionAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RevisionAction::Add => "add",
+            RevisionAction::Update => "update",
+            RevisionAction::Delete => "delete",
+            RevisionAction::Restore => "restore",
+            RevisionAction::Rename => "rename",
+        }
+    }
+}
+
+impl std::str::FromStr for RevisionAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "add" => Ok(RevisionAction::Add),
+            "update" => Ok(RevisionAction::Update),
+            "delete" => Ok(RevisionAction::Delete),
+            "restore" => Ok(RevisionAction::Restore),
+            "rename" => Ok(RevisionAction::Rename),
+            _ => Err(format!("unknown revision action: {}", s)),
+        }
+    }
+}
+
+/// One recorded change to a joke. `joke` is the joke content
+/// the change is about: the new content for additions,
+/// updates and restores, and the removed content for
+/// deletions, so that restoring any revision brings back
+/// exactly what it shows.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Revision {
+    #[schema(example = 17)]
+    pub revision: i32,
+    #[schema(example = "boo")]
+    pub id: String,
+    pub action: RevisionAction,
+    #[schema(example = "John Smith <johnsmith@example.org>")]
+    pub author: String,
+    pub created: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub joke: Option<Joke>,
+    #[schema(value_type = Object, example = json!({
+        "answer_who": {"old": "You don't have to cry about it!", "new": "Don't cry!"}
+    }))]
+    pub diff: serde_json::Value,
+}
+
+impl Revision {
+    pub fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let action: String = row.get("action");
+        let action = action
+            .parse()
+            .map_err(|e: String| sqlx::Error::Decode(e.into()))?;
+        let joke: Option<sqlx::types::Json<Joke>> = row.get("joke");
+        Ok(Revision {
+            revision: row.get("revision"),
+            id: row.get("id"),
+            action,
+            author: row.get("author"),
+            created: row.get("created"),
+            joke: joke.map(|j| j.0),
+            diff: row.get("diff"),
+        })
+    }
+}
+
+/// Field-by-field difference between two versions of a joke,
+/// as `{field: {"old": ..., "new": ...}}` for each changed field.
+pub fn joke_diff(old: Option<&Joke>, new: Option<&Joke>) -> serde_json::Value {
+    let old = old.map(Joke::canonical_json).unwrap_or_default();
+    let new = new.map(Joke::canonical_json).unwrap_or_default();
+    let mut fields: Vec<&String> = old.keys().chain(new.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    let mut diff = serde_json::Map::new();
+    for field in fields {
+        let old_value = old.get(field).cloned().unwrap_or_default();
+        let new_value = new.get(field).cloned().unwrap_or_default();
+        if old_value != new_value {
+            diff.insert(
+                field.clone(),
+                serde_json::json!({"old": old_value, "new": new_value}),
+            );
+        }
+    }
+    serde_json::Value::Object(diff)
+}
@@ -0,0 +1,147 @@
+//! Scripts: jokes told as a list of turns, for those that don't
+//! fit the classic five lines, such as "interrupting cow" jokes
+//! where the teller cuts in, or jokes with a pause. Jokes
+//! without a script are told the classic way.
+
+use crate::*;
+
+pub const MAX_SCRIPT_TURNS: usize = 20;
+/// Ends an interrupted line when it is told.
+const CUT_OFF: char = '—';
+
+/// Who says a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Speaker {
+    /// The one telling the joke.
+    Teller,
+    /// The one answering the door.
+    Tellee,
+    /// Not said at all: a stage direction, like a pause.
+    Direction,
+}
+
+impl Speaker {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Speaker::Teller => "teller",
+            Speaker::Tellee => "tellee",
+            Speaker::Direction => "direction",
+        }
+    }
+}
+
+/// One line of a joke.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Turn {
+    #[schema(example = "tellee")]
+    pub speaker: Speaker,
+    #[schema(example = "Interrupting cow wh")]
+    pub line: String,
+    /// This line cuts off the one before it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupts: bool,
+}
+
+impl Turn {
+    pub fn new(speaker: Speaker, line: impl Into<String>) -> Self {
+        Turn {
+            speaker,
+            line: line.into(),
+            interrupts: false,
+        }
+    }
+}
+
+/// The classic five lines of a joke without a script, in the
+/// joke's language.
+pub fn classic_turns(lang: &str, whos_there: &str, answer_who: &str) -> Vec<Turn> {
+    vec![
+        Turn::new(Speaker::Teller, knock_knock_line(lang)),
+        Turn::new(Speaker::Tellee, whos_there_line(lang)),
+        Turn::new(Speaker::Teller, format!("{}.", whos_there)),
+        Turn::new(Speaker::Tellee, who_who_line(lang, whos_there)),
+        Turn::new(Speaker::Teller, answer_who),
+    ]
+}
+
+/// Turns as they are told: stage directions in parentheses, and
+/// lines that are interrupted ending in a dash.
+pub fn told_turns(turns: &[Turn]) -> Vec<Turn> {
+    let mut told: Vec<Turn> = turns.to_vec();
+    for i in 1..told.len() {
+        if told[i].interrupts && !told[i - 1].line.ends_with(CUT_OFF) {
+            told[i - 1].line.push(CUT_OFF);
+        }
+    }
+    for turn in told.iter_mut() {
+        if turn.speaker == Speaker::Direction {
+            turn.line = format!("({})", turn.line);
+        }
+    }
+    told
+}
+
+/// Turns read back from told lines, as in a fortune file. The
+/// teller and tellee take turns, starting with the teller.
+pub fn parse_turns(lines: &[&str]) -> Vec<Turn> {
+    let mut turns: Vec<Turn> = Vec::with_capacity(lines.len());
+    let mut speaker = Speaker::Teller;
+    let mut cut_off = false;
+    for line in lines {
+        if let Some(direction) = line.strip_prefix('(').and_then(|l| l.strip_suffix(')')) {
+            turns.push(Turn::new(Speaker::Direction, direction.trim()));
+            continue;
+        }
+        turns.push(Turn {
+            speaker,
+            line: line.to_string(),
+            interrupts: cut_off,
+        });
+        cut_off = line.ends_with(CUT_OFF);
+        speaker = match speaker {
+            Speaker::Teller => Speaker::Tellee,
+            _ => Speaker::Teller,
+        };
+    }
+    turns
+}
+
+/// Tidy a script, dropping an empty one.
+pub fn normalize_script(script: Option<Vec<Turn>>) -> Option<Vec<Turn>> {
+    let script: Vec<Turn> = script?
+        .into_iter()
+        .map(|turn| Turn {
+            line: turn.line.trim().to_string(),
+            ..turn
+        })
+        .collect();
+    if script.is_empty() {
+        None
+    } else {
+        Some(script)
+    }
+}
+
+pub fn check_script(script: &[Turn]) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if script.len() < 2 {
+        errors.push(FieldError::new("script", "must have at least 2 turns"));
+    } else if script.len() > MAX_SCRIPT_TURNS {
+        let message = format!("must have at most {} turns", MAX_SCRIPT_TURNS);
+        errors.push(FieldError::new("script", message));
+    }
+    if script.first().is_some_and(|turn| turn.interrupts) {
+        errors.push(FieldError::new(
+            "script",
+            "turn 1: has nothing to interrupt",
+        ));
+    }
+    for (i, turn) in script.iter().enumerate() {
+        if let Some(error) = check_text("script", &turn.line, MAX_ANSWER_WHO_LEN) {
+            let message = format!("turn {}: {}", i + 1, error.message);
+            errors.push(FieldError::new("script", message));
+        }
+    }
+    errors
+}
@@ -5,8 +5,15 @@ async fn handler_404() -> Response {
 }
 
 pub const SESSION_ERROR_KEY: &str = "session_error";
+pub const SESSION_FIELD_ERRORS_KEY: &str = "session_field_errors";
+pub const SESSION_SIMILAR_KEY: &str = "session_similar";
 
-pub async fn startup(ip: String) {
+pub async fn startup(
+    ip: String,
+    trash_retention: TimeDelta,
+    duplicates: DuplicatePolicy,
+    family_safe: bool,
+) {
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
@@ -29,6 +36,8 @@ pub async fn startup(ip: String) {
         std::process::exit(1);
     });
 
+    let views = ViewLog::spawn(JokeBase(jokebase.0.clone()));
+
     let jwt_keys = make_jwt_keys().await.unwrap_or_else(|_| {
         tracing::error!("jwt keys");
         std::process::exit(1);
@@ -39,7 +48,16 @@ pub async fn startup(ip: String) {
         std::process::exit(1);
     });
 
-    let state = Arc::new(RwLock::new(AppState::new(jokebase, jwt_keys, reg_key)));
+    let state = Arc::new(RwLock::new(AppState::new(
+        jokebase,
+        jwt_keys,
+        reg_key,
+        trash_retention,
+        duplicates,
+        family_safe,
+        views,
+    )));
+    spawn_purge_job(state.clone());
 
     let cors = cors::CorsLayer::new()
         .allow_methods([Method::GET])
@@ -53,11 +71,48 @@ pub async fn startup(ip: String) {
 
     let apis = Router::new()
         .route("/jokes", get(jokes))
+        .route("/jokes/top", get(top_jokes))
+        .route("/jokes/import", post(import_jokes))
+        .route("/jokes/import/text", post(import_text_jokes))
+        .route("/export", get(export_jokes))
+        .route("/export/fortune", get(export_fortunes))
+        .route("/restore", post(restore_jokes))
         .route("/joke", get(joke))
         .route("/joke/:id", get(get_joke))
         .route("/joke/add", post(post_joke))
         .route("/joke/:id", delete(delete_joke))
         .route("/joke/:id", put(update_joke))
+        .route("/joke/:id", patch(patch_joke))
+        .route("/joke/:id/tags", patch(edit_joke_tags))
+        .route("/joke/:id/rename", post(rename_joke))
+        .route("/joke/:id/rating", post(rate_joke))
+        .route("/joke/:id/related", get(related_jokes))
+        .route("/joke/:id/translations", get(joke_translations))
+        .route("/joke/:id/history", get(joke_history))
+        .route("/joke/:id/analytics", get(joke_analytics))
+        .route("/joke/:id/history/:revision/restore", post(restore_joke))
+        .route("/joke/:id/restore", post(undelete_joke))
+        .route("/joke/:id/review", post(review_joke))
+        .route("/duplicates", get(duplicate_jokes))
+        .route("/review", get(review_queue))
+        .route("/tags", get(tags))
+        .route("/tags/report", get(tags_report))
+        .route("/tags/merge", post(merge_tags))
+        .route("/tags/aliases", get(tag_aliases))
+        .route("/tags/aliases/:alias", put(set_tag_alias))
+        .route("/tags/aliases/:alias", delete(delete_tag_alias))
+        .route("/tags/:tag/description", put(describe_tag))
+        .route("/tags/:tag/rename", post(rename_tag))
+        .route("/collections", get(collections))
+        .route("/collections", post(post_collection))
+        .route("/collections/:id", get(get_collection))
+        .route("/collections/:id", put(update_collection))
+        .route("/collections/:id", delete(delete_collection))
+        .route("/collections/:id/joke", get(collection_joke))
+        .route("/analytics", get(analytics))
+        .route("/analytics/report", get(analytics_report))
+        .route("/trash", get(trash))
+        .route("/trash/purge", post(purge_trash))
         .route("/register", get(register));
 
     let swagger_ui = SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi());
@@ -69,6 +124,7 @@ pub async fn startup(ip: String) {
         .route("/index.html", get(handler_index))
         .route("/tell", get(handler_tell))
         .route("/add", get(handler_add))
+        .route("/rate", get(handler_rate))
         .route_service("/knock-knock.css", stylesheet)
         .route_service("/favicon.ico", favicon)
         .merge(swagger_ui)
@@ -76,6 +132,7 @@ pub async fn startup(ip: String) {
         .merge(rapidoc_ui)
         .nest("/api/v1", apis)
         .fallback(handler_404)
+        .layer(middleware::from_fn(redirect_renamed))
         .layer(cors)
         .layer(session_layer)
         .layer(trace_layer)
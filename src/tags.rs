@@ -0,0 +1,98 @@
+//! Managing tags across the jokebase: counts, descriptions,
+//! renaming and merging, and aliases that are replaced by the
+//! tag they stand for whenever a joke is written.
+
+use crate::*;
+
+pub const MAX_TAG_DESCRIPTION_LEN: usize = 500;
+
+/// A tag and how it is used.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TagInfo {
+    #[schema(example = "animal")]
+    pub tag: String,
+    /// Live jokes with this tag.
+    #[schema(example = 12)]
+    pub count: i64,
+    #[schema(example = "Jokes about animals")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Tags written as this one.
+    #[schema(example = json!(["animals"]))]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+impl TagInfo {
+    pub fn unused(tag: String) -> Self {
+        TagInfo {
+            tag,
+            count: 0,
+            description: None,
+            aliases: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct TagRename {
+    #[schema(example = "animal")]
+    pub to: String,
+}
+
+/// Tags to fold into one. Each merged tag becomes an alias of
+/// `into`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct TagMerge {
+    #[schema(example = json!(["animals", "critters"]))]
+    pub tags: Vec<String>,
+    #[schema(example = "animal")]
+    pub into: String,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct TagDescription {
+    /// New description; none or empty to remove it.
+    #[schema(example = "Jokes about animals")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct AliasTarget {
+    /// Tag the alias stands for.
+    #[schema(example = "animal")]
+    pub tag: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TagAlias {
+    #[schema(example = "animals")]
+    pub alias: String,
+    #[schema(example = "animal")]
+    pub tag: String,
+}
+
+/// Tag names as given in requests, tidied like joke tags.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Check one tag name, reporting problems against `field`.
+pub fn check_tag_name(field: &str, tag: &str) -> Option<FieldError> {
+    if tag.is_empty() {
+        return Some(FieldError::from_key(field, "field.empty", &[]));
+    }
+    check_tags(&HashSet::from([tag.to_string()]))
+        .into_iter()
+        .next()
+        .map(|error| error.for_field(field))
+}
+
+/// Fail with `TagInvalid` if there are any problems.
+pub fn tag_errors(errors: Vec<FieldError>) -> Result<(), JokeBaseErr> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(JokeBaseErr::TagInvalid(errors))
+    }
+}
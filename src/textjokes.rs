@@ -0,0 +1,170 @@
+//! Reader for jokes written out as numbered plain text, as in
+//! `joke-working/jokes-la-jolla.txt`:
+//!
+//! ```text
+//! tag: food
+//!
+//! 1. Knock, knock.
+//! Who’s there?
+//! Banana.
+//! Banana who?
+//! Banana split!
+//! ```
+//!
+//! A `tag:` line applies to the jokes after it (`misc` adds no
+//! tag), and every joke is also tagged `kids`.
+
+use crate::*;
+
+/// Text layouts that jokes can be imported from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TextFormat {
+    /// Numbered jokes with `tag:` lines.
+    #[default]
+    Numbered,
+    /// A `fortune(6)` file.
+    Fortune,
+}
+
+/// Jokes read from text, with complaints about the parts that
+/// could not be read.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct TextJokes {
+    pub jokes: Vec<Joke>,
+    #[schema(example = r#"["line 12: expected blank line after joke"]"#)]
+    pub warnings: Vec<String>,
+}
+
+/// Result of reading a text import and checking it against the
+/// jokebase. `results` is missing on a dry run.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TextImportReport {
+    #[serde(flatten)]
+    pub parsed: TextJokes,
+    /// Ids of parsed jokes that are already in use.
+    #[schema(example = r#"["banana"]"#)]
+    pub collisions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub results: Option<Vec<ImportResult>>,
+}
+
+/// The last word of an answer, used to tell apart jokes with
+/// the same "who's there".
+pub fn last_word(answer: &str) -> Option<String> {
+    let answer = answer.trim_end_matches(|c: char| !c.is_ascii_alphabetic());
+    let (i, before) = answer
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !c.is_ascii_alphabetic())?;
+    let start = i + before.len_utf8();
+    if before == ' ' && start < answer.len() {
+        Some(slugify(&answer[start..]))
+    } else {
+        None
+    }
+}
+
+fn is_start_line(line: &str) -> bool {
+    match line.split_once(". ") {
+        Some((number, rest)) => {
+            !number.is_empty()
+                && number.chars().all(|c| c.is_ascii_digit())
+                && rest == "Knock, knock."
+        }
+        None => false,
+    }
+}
+
+impl TextJokes {
+    fn push(&mut self, mut joke: Joke) {
+        // On a collision both jokes get the last word of their
+        // answer added to the id, as in `olive-you`/`olive-too`.
+        if let Some(i) = self.jokes.iter().position(|j| j.id == joke.id) {
+            let mut other = self.jokes.remove(i);
+            self.amend_id(&mut other);
+            self.jokes.push(other);
+            self.amend_id(&mut joke);
+        }
+        self.jokes.push(joke);
+    }
+
+    fn amend_id(&mut self, joke: &mut Joke) {
+        match last_word(&joke.answer_who) {
+            Some(word) => joke.id = format!("{}-{}", joke.id, word),
+            None => self.warnings.push(format!(
+                "{}: cannot make distinct id from answer: {}",
+                joke.id, joke.answer_who,
+            )),
+        }
+    }
+}
+
+pub fn parse_text(text: &str, format: TextFormat, source: Option<&str>) -> TextJokes {
+    match format {
+        TextFormat::Numbered => parse_text_jokes(text, source),
+        TextFormat::Fortune => parse_fortunes(text, source),
+    }
+}
+
+pub fn parse_text_jokes(text: &str, source: Option<&str>) -> TextJokes {
+    let mut parsed = TextJokes::default();
+    let mut category = "misc".to_string();
+    let mut lines = text.lines().map(str::trim).enumerate();
+    let end_of_file = format!("unexpected end of file after line {}", text.lines().count());
+
+    while let Some((_, line)) = lines.next() {
+        if let Some(tag) = line.strip_prefix("tag: ") {
+            category = tag.to_string();
+            continue;
+        }
+        if !is_start_line(line) {
+            continue;
+        }
+        let mut next = || lines.next();
+
+        let Some((lineno, line)) = next() else {
+            parsed.warnings.push(end_of_file);
+            break;
+        };
+        if line != "Who’s there?" && line != "Who's there?" {
+            parsed
+                .warnings
+                .push(format!("line {}: expected \"Who’s there?\"", lineno + 1));
+            continue;
+        }
+        let Some((lineno, line)) = next() else {
+            parsed.warnings.push(end_of_file);
+            break;
+        };
+        let Some(who) = line.strip_suffix(['!', '.']) else {
+            parsed
+                .warnings
+                .push(format!("line {}: cannot find who is there", lineno + 1));
+            continue;
+        };
+        let who = who.to_string();
+        let (Some(_question), Some((_, answer))) = (next(), next()) else {
+            parsed.warnings.push(end_of_file);
+            break;
+        };
+        let answer = answer.to_string();
+        // The last joke need not be followed by a blank line.
+        if let Some((lineno, blank)) = next() {
+            if !blank.is_empty() {
+                parsed.warnings.push(format!(
+                    "line {}: expected blank line after joke",
+                    lineno + 1
+                ));
+                continue;
+            }
+        }
+
+        let mut tags = vec!["kids"];
+        if category != "misc" {
+            tags.push(&category);
+        }
+        parsed.push(Joke::new(&slugify(&who), &who, &answer, &tags, source));
+    }
+    parsed
+}
@@ -0,0 +1,33 @@
+use crate::*;
+
+/// How often the trash is checked for jokes past retention.
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+/// Longest the trash can be kept, in days.
+pub const MAX_RETENTION_DAYS: i64 = 100 * 366;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TrashedJoke {
+    #[serde(flatten)]
+    pub joke: Joke,
+    pub deleted: DateTime<Utc>,
+}
+
+/// Periodically purge jokes that have been in the trash for
+/// longer than the configured retention.
+pub fn spawn_purge_job(state: SharedAppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let mut appstate = state.write().await;
+            let before = Utc::now() - appstate.trash_retention;
+            match appstate.jokebase.purge_trash(before).await {
+                Ok(ids) if !ids.is_empty() => {
+                    tracing::info!("purged from trash: {}", ids.join(", "))
+                }
+                Ok(_) => (),
+                Err(e) => tracing::error!("trash purge: {}", e),
+            }
+        }
+    });
+}
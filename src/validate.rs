@@ -0,0 +1,282 @@
+//! Checking and tidying jokes before they go into the
+//! jokebase. Everything that adds or changes a joke goes
+//! through `validate_joke`.
+
+use crate::*;
+
+pub const MAX_ID_LEN: usize = 64;
+/// Made-up ids are kept this short to leave room for a suffix.
+pub const MAX_SLUG_LEN: usize = 40;
+pub const MAX_WHOS_THERE_LEN: usize = 100;
+pub const MAX_ANSWER_WHO_LEN: usize = 500;
+pub const MAX_TAG_LEN: usize = 32;
+pub const MAX_TAGS: usize = 20;
+pub const MAX_SOURCE_LEN: usize = 500;
+
+/// A problem with one field of a joke.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct FieldError {
+    #[schema(example = "id")]
+    pub field: String,
+    #[schema(example = "must be lowercase letters and digits, separated by single hyphens")]
+    pub message: String,
+    /// Message catalog key and arguments the message was made
+    /// from, for showing it on web pages in other locales.
+    #[serde(skip)]
+    pub key: Option<(&'static str, Vec<String>)>,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.into(),
+            key: None,
+        }
+    }
+
+    /// Error with a message from the message catalogs, in
+    /// English here.
+    pub fn from_key(field: &str, key: &'static str, args: &[&str]) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: Locale::En.fill(key, args),
+            key: Some((key, args.iter().map(|arg| arg.to_string()).collect())),
+        }
+    }
+
+    /// The same error, reported against another field.
+    pub fn for_field(self, field: &str) -> Self {
+        FieldError {
+            field: field.to_string(),
+            ..self
+        }
+    }
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+pub fn format_field_errors(errors: &[FieldError]) -> String {
+    let errors: Vec<String> = errors.iter().map(FieldError::to_string).collect();
+    errors.join("; ")
+}
+
+/// True for ids like `olive-you`: lowercase ASCII letters
+/// and digits, in runs separated by single hyphens.
+pub fn is_slug(id: &str) -> bool {
+    id.split('-').all(|run| {
+        !run.is_empty()
+            && run
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    })
+}
+
+/// Id-shaped version of some text: its ASCII letters and
+/// digits, lowercased, with a hyphen for each run of anything
+/// else. Long text is cut short at a word break.
+pub fn slugify(text: &str) -> String {
+    let text = text.to_lowercase();
+    let words = text
+        .split(|c: char| !c.is_ascii_lowercase() && !c.is_ascii_digit())
+        .filter(|word| !word.is_empty());
+    let mut slug = String::new();
+    for word in words {
+        if slug.len() + word.len() + 1 > MAX_SLUG_LEN {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(word);
+    }
+    if slug.is_empty() {
+        slug.push_str("joke");
+    }
+    slug
+}
+
+pub fn check_id(id: &str) -> Option<FieldError> {
+    if id.is_empty() {
+        Some(FieldError::from_key("id", "field.empty", &[]))
+    } else if id.len() > MAX_ID_LEN {
+        Some(FieldError::from_key(
+            "id",
+            "field.too_long",
+            &[&MAX_ID_LEN.to_string()],
+        ))
+    } else if !is_slug(id) {
+        Some(FieldError::from_key("id", "field.not_slug", &[]))
+    } else {
+        None
+    }
+}
+
+/// A number of days given in a request, from 0 to `max`.
+pub fn check_days(field: &str, days: i64, max: i64) -> Result<TimeDelta, FieldError> {
+    (0..=max)
+        .contains(&days)
+        .then(|| TimeDelta::try_days(days))
+        .flatten()
+        .ok_or_else(|| FieldError::from_key(field, "field.range", &["0", &max.to_string()]))
+}
+
+/// The time `days` days ago, for a request parameter of at
+/// most `max` days.
+pub fn days_ago(field: &str, days: i64, max: i64) -> Result<DateTime<Utc>, JokeBaseErr> {
+    let invalid = |error| JokeBaseErr::ParamInvalid(vec![error]);
+    let delta = check_days(field, days, max).map_err(invalid)?;
+    Utc::now()
+        .checked_sub_signed(delta)
+        .ok_or_else(|| invalid(FieldError::from_key(field, "field.too_far_back", &[])))
+}
+
+pub fn check_text(field: &str, text: &str, max_len: usize) -> Option<FieldError> {
+    if text.is_empty() {
+        Some(FieldError::from_key(field, "field.empty", &[]))
+    } else if text.chars().count() > max_len {
+        Some(FieldError::from_key(
+            field,
+            "field.too_long",
+            &[&max_len.to_string()],
+        ))
+    } else {
+        None
+    }
+}
+
+/// Tags trimmed and lowercased, without empty or repeated
+/// ones.
+pub fn normalize_tags(tags: &HashSet<String>) -> HashSet<String> {
+    tags.iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+pub fn check_tags(tags: &HashSet<String>) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if tags.len() > MAX_TAGS {
+        errors.push(FieldError::from_key(
+            "tags",
+            "field.too_many_tags",
+            &[&MAX_TAGS.to_string()],
+        ));
+    }
+    let mut tags: Vec<&String> = tags.iter().collect();
+    tags.sort();
+    for tag in tags {
+        if tag.chars().count() > MAX_TAG_LEN {
+            let args = [tag.as_str(), &MAX_TAG_LEN.to_string()];
+            errors.push(FieldError::from_key("tags", "field.tag_too_long", &args));
+        } else if tag.contains(',') {
+            errors.push(FieldError::from_key("tags", "field.tag_comma", &[tag]));
+        }
+    }
+    errors
+}
+
+/// Sources may be free text, such as the name of the person
+/// who told the joke, so only sources that look like URLs are
+/// checked as URLs.
+fn looks_like_url(source: &str) -> bool {
+    source.contains("://") || source.starts_with("www.")
+}
+
+/// Check a URL, giving the message key for any problem.
+fn check_url(url: &str) -> Result<(), &'static str> {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return Err("field.url_scheme");
+    };
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return Err("field.url_scheme");
+    }
+    if url.chars().any(char::is_whitespace) {
+        return Err("field.url_spaces");
+    }
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_char = |c: char| c.is_ascii_alphanumeric() || ".-:[]".contains(c);
+    if host.is_empty() || !host.chars().all(host_char) {
+        return Err("field.url_host");
+    }
+    Ok(())
+}
+
+pub fn check_source(source: &str) -> Option<FieldError> {
+    if let Some(error) = check_text("source", source, MAX_SOURCE_LEN) {
+        return Some(error);
+    }
+    if looks_like_url(source) {
+        if let Err(key) = check_url(source) {
+            return Some(FieldError::from_key("source", key, &[]));
+        }
+    }
+    None
+}
+
+/// Tidy a joke and check it, giving back the joke as it
+/// should be stored or the problems with each field.
+pub fn validate_joke(joke: Joke) -> Result<Joke, JokeBaseErr> {
+    let joke = Joke {
+        id: joke.id.trim().to_string(),
+        whos_there: joke.whos_there.trim().to_string(),
+        answer_who: joke.answer_who.trim().to_string(),
+        tags: joke
+            .tags
+            .map(|tags| normalize_tags(&tags))
+            .filter(|tags| !tags.is_empty()),
+        source: joke
+            .source
+            .map(|source| source.trim().to_string())
+            .filter(|source| !source.is_empty()),
+        script: normalize_script(joke.script),
+        lang: joke.lang.trim().to_lowercase(),
+        translation_of: joke
+            .translation_of
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty()),
+        audience: joke.audience,
+        flagged: joke.flagged,
+        rating: joke.rating,
+    };
+
+    let mut errors: Vec<FieldError> = [
+        check_id(&joke.id),
+        check_text("whos_there", &joke.whos_there, MAX_WHOS_THERE_LEN),
+        check_text("answer_who", &joke.answer_who, MAX_ANSWER_WHO_LEN),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if let Some(tags) = &joke.tags {
+        errors.extend(check_tags(tags));
+    }
+    if let Some(source) = &joke.source {
+        errors.extend(check_source(source));
+    }
+    if let Some(script) = &joke.script {
+        errors.extend(check_script(script));
+    }
+    errors.extend(check_lang(&joke.lang));
+    if let Some(original) = &joke.translation_of {
+        if original == &joke.id {
+            errors.push(FieldError::from_key(
+                "translation_of",
+                "field.self_translation",
+                &[],
+            ));
+        } else if let Some(error) = check_id(original) {
+            errors.push(error.for_field("translation_of"));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(joke)
+    } else {
+        Err(JokeBaseErr::JokeInvalid(errors))
+    }
+}
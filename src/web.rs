@@ -3,88 +3,359 @@ use crate::*;
 #[derive(Template)]
 #[template(path = "index.html")]
 pub struct IndexTemplate<'a> {
+    msg: Locale,
     joke: Option<&'a Joke>,
     tags: Option<String>,
+    related: Vec<RelatedJoke>,
+    /// Other languages the joke can be told in.
+    languages: Vec<String>,
+    /// Jokes the one just added looks like a copy of.
+    similar: Vec<String>,
+    collection: Option<Collection>,
+    random: bool,
     stylesheet: &'static str,
     error: Option<String>,
 }
 
 impl<'a> IndexTemplate<'a> {
-    fn joke(joke: &'a Joke) -> Self {
+    fn joke(
+        msg: Locale,
+        joke: &'a Joke,
+        related: Vec<RelatedJoke>,
+        languages: Vec<String>,
+    ) -> Self {
         Self {
+            msg,
             joke: Some(joke),
             tags: joke.tags.as_ref().map(format_tags),
+            related,
+            languages,
+            similar: Vec::new(),
+            collection: None,
+            random: false,
             stylesheet: "/knock-knock.css",
             error: None,
         }
     }
 
-    fn error(error: String) -> Self {
+    /// Point out the jokes the one just added looks like.
+    fn similar_to(self, similar: Vec<String>) -> Self {
+        Self { similar, ..self }
+    }
+
+    /// Show the joke as told from a collection, so that the next
+    /// joke comes from the same collection.
+    fn in_collection(self, collection: Option<Collection>, order: CollectionOrder) -> Self {
+        Self {
+            collection,
+            random: order == CollectionOrder::Random,
+            ..self
+        }
+    }
+
+    fn error(msg: Locale, error: String) -> Self {
         Self {
+            msg,
             joke: None,
             tags: None,
+            related: Vec::new(),
+            languages: Vec::new(),
+            similar: Vec::new(),
+            collection: None,
+            random: false,
             stylesheet: "/knock-knock.css",
             error: Some(error),
         }
     }
+
+    fn locales(&self) -> [Locale; 2] {
+        LOCALES
+    }
+}
+
+/// Related jokes shown under the joke on the front page.
+const RELATED_ON_PAGE: usize = 4;
+
+#[derive(Template)]
+#[template(path = "jokes.html")]
+pub struct JokesTemplate<'a> {
+    msg: Locale,
+    cards: Vec<(&'a Joke, Option<String>)>,
+    stylesheet: &'static str,
+}
+
+impl<'a> JokesTemplate<'a> {
+    pub fn new(jokes: &'a [Joke]) -> Self {
+        Self {
+            msg: Locale::En,
+            cards: jokes
+                .iter()
+                .map(|joke| (joke, joke.tags.as_ref().map(format_tags)))
+                .collect(),
+            stylesheet: "/knock-knock.css",
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "analytics.html")]
+pub struct AnalyticsTemplate<'a> {
+    days: i64,
+    channels: Vec<&'a str>,
+    rows: Vec<(&'a str, i64, Vec<i64>)>,
+    stylesheet: &'static str,
+}
+
+impl<'a> AnalyticsTemplate<'a> {
+    /// Table of view counts, with a column for each channel
+    /// any joke was told through.
+    pub fn new(counts: &'a [ViewCount], days: i64) -> Self {
+        let channels: BTreeSet<&str> = counts
+            .iter()
+            .flat_map(|c| c.channels.keys().map(String::as_str))
+            .collect();
+        let channels: Vec<&str> = channels.into_iter().collect();
+        let rows = counts
+            .iter()
+            .map(|c| {
+                let views = channels
+                    .iter()
+                    .map(|&channel| c.channels.get(channel).copied().unwrap_or(0))
+                    .collect();
+                (c.id.as_str(), c.total, views)
+            })
+            .collect();
+        Self {
+            days,
+            channels,
+            rows,
+            stylesheet: "/knock-knock.css",
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "tags.html")]
+pub struct TagsTemplate<'a> {
+    tags: &'a [TagInfo],
+    stylesheet: &'static str,
+}
+
+impl<'a> TagsTemplate<'a> {
+    pub fn new(tags: &'a [TagInfo]) -> Self {
+        Self {
+            tags,
+            stylesheet: "/knock-knock.css",
+        }
+    }
 }
 
 #[derive(Deserialize)]
 pub struct IndexParams {
     id: Option<String>,
+    collection: Option<String>,
+    #[serde(default)]
+    order: CollectionOrder,
+}
+
+/// Session key for the position of the next joke to tell from
+/// a collection.
+fn collection_position_key(collection: &str) -> String {
+    format!("collection_position:{}", collection)
+}
+
+/// Languages to tell jokes and show pages in on the web, best
+/// first: one picked earlier, then those in `Accept-Language`.
+fn web_languages(cookie: LangCookie, accepted: AcceptLanguage) -> Vec<String> {
+    LangParams { lang: cookie.0 }.preferences(accepted)
 }
 
+/// The front page. A language picked with `?lang=` is kept in
+/// a cookie for later visits.
 pub async fn handler_index(
     State(appstate): HandlerAppState,
     Query(params): Query<IndexParams>,
+    Query(lang): Query<LangParams>,
+    cookie: LangCookie,
+    accepted: AcceptLanguage,
+    session: Session,
+) -> Response {
+    let set_cookie = lang.lang.as_deref().and_then(LangCookie::set);
+    let cookie = match &set_cookie {
+        Some(_) => LangCookie(lang.lang.map(|lang| lang.trim().to_lowercase())),
+        None => cookie,
+    };
+    let langs = web_languages(cookie, accepted);
+    let page = index_page(appstate, params, &langs, session).await;
+    let vary = [(header::VARY, PAGE_LANG_VARY)];
+    (vary, AppendHeaders(set_cookie), page).into_response()
+}
+
+async fn index_page(
+    appstate: SharedAppState,
+    params: IndexParams,
+    langs: &[String],
+    session: Session,
 ) -> Response {
     let appstate = appstate.read().await;
     let jokebase = &appstate.jokebase;
+    let msg = Locale::choose(langs);
+    // The web follows the server's family-safe mode only.
+    let family_safe = appstate.family_safe;
 
     let joke = if let Some(id) = params.id {
-        jokebase.get(&id).await
+        jokebase
+            .get(&id)
+            .await
+            .and_then(|joke| check_family_safe(joke, family_safe))
+    } else if let Some(collection) = &params.collection {
+        let key = collection_position_key(collection);
+        let position: usize = session.get(&key).await.unwrap_or(None).unwrap_or(0);
+        match jokebase
+            .collection_joke(collection, params.order, position, family_safe)
+            .await
+        {
+            Ok((joke, position)) => {
+                let _ = session.insert(&key, position + 1).await;
+                let mut url = format!("/?id={}&collection={}", joke.id, collection);
+                if params.order == CollectionOrder::Random {
+                    url.push_str("&order=random");
+                }
+                return Redirect::to(&url).into_response();
+            }
+            e => e.map(|(joke, _)| joke),
+        }
     } else {
-        match jokebase.get_random().await {
+        match jokebase.get_random(true, family_safe).await {
             Ok(joke) => return Redirect::to(&format!("/?id={}", joke.id)).into_response(),
             e => e,
         }
     };
 
+    let joke = match joke {
+        Ok(joke) => jokebase.localize(joke, langs, family_safe).await,
+        e => e,
+    };
     match joke {
-        Ok(joke) => (StatusCode::OK, IndexTemplate::joke(&joke)).into_response(),
-        Err(JokeBaseErr::JokeDoesNotExist(id)) => (
-            StatusCode::OK,
-            IndexTemplate::error(format!("cannot find joke {}", id)),
-        )
-            .into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            IndexTemplate::error(e.to_string()),
-        )
-            .into_response(),
+        Ok(joke) => {
+            appstate.views.record(&joke.id, Channel::Web);
+            let related = jokebase
+                .related(&joke.id, RELATED_ON_PAGE, family_safe)
+                .await;
+            let related = related.unwrap_or_else(|e| {
+                tracing::warn!("related jokes for {}: {}", joke.id, e);
+                Vec::new()
+            });
+            let collection = match &params.collection {
+                Some(collection) => jokebase.get_collection(collection).await.ok(),
+                None => None,
+            };
+            let languages: Vec<String> = match jokebase.translations(&joke.id, family_safe).await {
+                Ok(translations) => translations
+                    .into_iter()
+                    .map(|translation| translation.lang)
+                    .filter(|lang| *lang != joke.lang)
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            let similar: Vec<String> = session
+                .remove(SESSION_SIMILAR_KEY)
+                .await
+                .unwrap_or(None)
+                .unwrap_or_default();
+            let page = IndexTemplate::joke(msg, &joke, related, languages)
+                .in_collection(collection, params.order)
+                .similar_to(similar);
+            (StatusCode::OK, page).into_response()
+        }
+        Err(JokeBaseErr::JokeRenamed(id)) => {
+            Redirect::permanent(&format!("/?id={}", id)).into_response()
+        }
+        Err(e) => {
+            let error = match &e {
+                JokeBaseErr::NoJoke if params.collection.is_some() => {
+                    msg.t("error.empty_collection").to_string()
+                }
+                e => msg.error(e),
+            };
+            (e.status(), IndexTemplate::error(msg, error)).into_response()
+        }
     }
 }
 
 #[derive(Template)]
 #[template(path = "tell.html")]
 pub struct TellTemplate {
+    msg: Locale,
     stylesheet: &'static str,
     error: Option<String>,
+    field_errors: Vec<FieldError>,
 }
 
 impl TellTemplate {
-    fn new(error: Option<String>) -> Self {
+    fn new(msg: Locale, error: Option<String>, field_errors: Vec<FieldError>) -> Self {
         Self {
+            msg,
             stylesheet: "/knock-knock.css",
             error,
+            field_errors,
         }
     }
+
+    /// Message for the first problem with the given joke field.
+    fn field_error(&self, field: &str) -> Option<&str> {
+        self.field_errors
+            .iter()
+            .find(|e| e.field == field)
+            .map(|e| e.message.as_str())
+    }
 }
 
-pub async fn handler_tell(session: Session) -> Response {
+pub async fn handler_tell(
+    cookie: LangCookie,
+    accepted: AcceptLanguage,
+    session: Session,
+) -> Response {
+    let msg = Locale::choose(&web_languages(cookie, accepted));
     let error: Option<String> = session.get(SESSION_ERROR_KEY).await.unwrap_or(None).clone();
     let _ = session.remove::<Option<String>>(SESSION_ERROR_KEY).await;
-    (StatusCode::OK, TellTemplate::new(error)).into_response()
+    let field_errors: Vec<FieldError> = session
+        .remove(SESSION_FIELD_ERRORS_KEY)
+        .await
+        .unwrap_or(None)
+        .unwrap_or_default();
+    let page = TellTemplate::new(msg, error, field_errors);
+    let vary = [(header::VARY, PAGE_LANG_VARY)];
+    (StatusCode::OK, vary, page).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct RateParams {
+    id: String,
+    rating: i16,
+}
+
+pub async fn handler_rate(
+    State(appstate): HandlerAppState,
+    Query(params): Query<RateParams>,
+    cookie: LangCookie,
+    accepted: AcceptLanguage,
+    session: Session,
+) -> Response {
+    let msg = Locale::choose(&web_languages(cookie, accepted));
+    let voter = match session_voter(&session).await {
+        Ok(voter) => voter,
+        Err(e) => return (e.status(), IndexTemplate::error(msg, msg.error(&e))).into_response(),
+    };
+    let mut appstate = appstate.write().await;
+    match appstate
+        .jokebase
+        .rate(&params.id, &voter, params.rating)
+        .await
+    {
+        Ok(joke) => Redirect::to(&format!("/?id={}", joke.id)).into_response(),
+        Err(e) => (e.status(), IndexTemplate::error(msg, msg.error(&e))).into_response(),
+    }
 }
 
 #[derive(Deserialize)]
@@ -101,7 +372,7 @@ fn parse_tags(tags: Option<String>) -> Option<HashSet<String>> {
     if tags.is_empty() {
         return None;
     }
-    let tags: HashSet<String> = tags.split(',').map(str::trim).map(str::to_string).collect();
+    let tags = normalize_tags(&tags.split(',').map(str::to_string).collect());
     if tags.is_empty() {
         None
     } else {
@@ -118,32 +389,58 @@ fn parse_source(source: Option<String>) -> Option<String> {
     }
 }
 
+/// Revision author for jokes submitted through the web form.
+const WEB_AUTHOR: &str = "web";
+
 pub async fn handler_add(
     State(appstate): HandlerAppState,
     Query(params): Query<AddParams>,
+    cookie: LangCookie,
+    accepted: AcceptLanguage,
     session: Session,
 ) -> Response {
-    // XXX Condition user input.
+    let msg = Locale::choose(&web_languages(cookie, accepted));
     let joke = Joke {
-        id: params.id.clone(),
+        id: params.id,
         whos_there: params.who,
         answer_who: params.answer,
         tags: parse_tags(params.tags),
         source: parse_source(params.source),
+        script: None,
+        lang: default_lang(),
+        translation_of: None,
+        audience: Audience::default(),
+        flagged: Vec::new(),
+        rating: None,
     };
 
     let mut appstate = appstate.write().await;
-
-    match appstate.jokebase.add(joke).await {
-        Ok(()) => Redirect::to(&format!("/?id={}", params.id)).into_response(),
-        Err(JokeBaseErr::JokeBaseIoError(msg)) => {
-            (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response()
+    let duplicates = appstate.duplicates;
+    match appstate.jokebase.add(joke, duplicates, WEB_AUTHOR).await {
+        Ok((joke, similar)) => {
+            if !similar.is_empty() {
+                let _ = session.insert(SESSION_SIMILAR_KEY, similar).await;
+            }
+            Redirect::to(&format!("/?id={}", joke.id)).into_response()
         }
-        Err(JokeBaseErr::JokeExists(id)) => {
-            let error = Some(format!("joke {} already exists", id));
+        Err(e @ (JokeBaseErr::JokeExists(_) | JokeBaseErr::DuplicateJoke(_))) => {
+            let error = Some(msg.error(&e));
             let _ = session.insert(SESSION_ERROR_KEY, error).await;
             Redirect::to("/tell").into_response()
         }
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(JokeBaseErr::JokeInvalid(errors)) => {
+            // Catalog keys don't survive the session, so the
+            // messages go in already in the page's locale.
+            let errors: Vec<FieldError> = errors
+                .into_iter()
+                .map(|e| FieldError {
+                    message: msg.field_message(&e),
+                    ..e
+                })
+                .collect();
+            let _ = session.insert(SESSION_FIELD_ERRORS_KEY, errors).await;
+            Redirect::to("/tell").into_response()
+        }
+        Err(e) => (e.status(), IndexTemplate::error(msg, msg.error(&e))).into_response(),
     }
 }
@@ -1,13 +1,13 @@
-import json, random
-from urllib.request import Request, urlopen
+import json, random, time
+from urllib.request import HTTPCookieProcessor, Request, build_opener, urlopen
 from urllib.error import HTTPError
 
 url = "http://localhost:3000/api/v1"
 
 token = None
 
-def curl(endpoint, data=None, method="GET", expect_error=False, use_token = True):
-    headers = {"Content-Type":  "application/json"}
+def curl(endpoint, data=None, method="GET", expect_error=False, use_token = True, headers = None):
+    headers = {"Content-Type":  "application/json", **(headers or {})}
     if use_token:
         headers["Authorization"] = "Bearer " + token
     if data:
@@ -27,8 +27,8 @@ def curl(endpoint, data=None, method="GET", expect_error=False, use_token = True
         assert False
     except HTTPError as e:
         if expect_error:
-            r = json.loads(e.read())
-            return (e, r["error"])
+            assert e.headers["Content-Type"] == "application/problem+json"
+            return (e, json.loads(e.read()))
         assert False
 
 password = open("db/reg-password.txt", "r").read().strip()
@@ -78,8 +78,15 @@ e, r = curl(
     data = joke,
     expect_error = True,
 )
-assert str(e) == "HTTP Error 400: Bad Request"
-assert r == {"JokeExists": "boo"}
+assert str(e) == "HTTP Error 409: Conflict"
+assert r["type"].endswith("/problems/joke-exists")
+assert r["status"] == 409 and r["joke_id"] == "boo"
+print("failed successfully")
+
+print("getting missing joke: ", end="")
+e, r = curl("/joke/no-such-joke", expect_error = True)
+assert str(e) == "HTTP Error 404: Not Found"
+assert r["joke_id"] == "no-such-joke"
 print("failed successfully")
 
 def get_random_number():
@@ -109,12 +116,67 @@ try:
     assert str(e) == "HTTP Error 401: Unauthorized"
     print("failed successfully")
 
+    print("adding invalid joke: ", end="")
+    e, r = curl(
+        "/joke/add",
+        method = "POST",
+        data = {**joke, "id": "Random Number", "whos_there": " "},
+        expect_error = True,
+    )
+    assert str(e) == "HTTP Error 422: Unprocessable Entity"
+    assert sorted(f["field"] for f in r["errors"]) == ["id", "whos_there"]
+    print("failed successfully")
+
     print("adding new joke: ", end="")
-    curl(
+    added = curl(
         "/joke/add",
         method = "POST",
         data = joke,
     )
+    assert added["id"] == joke_id
+    print("ok")
+
+    print("adding joke without id: ", end="")
+    anonymous = curl(
+        "/joke/add",
+        method = "POST",
+        data = {k: v for k, v in joke.items() if k != "id"},
+    )
+    assert anonymous["id"].startswith("random-number")
+    curl(f"/joke/{anonymous['id']}", method = "DELETE")
+    print("ok")
+
+    print("making ids from curly quotes and accents: ", end="")
+    accented = curl("/joke/add", method = "POST", data = {
+        "whos_there": "Boo",
+        "answer_who": f"Boo {random_number}, café crème, I’m",
+    })
+    assert accented["id"].startswith("boo-")
+    curl(f"/joke/{accented['id']}", method = "DELETE")
+    text = "".join(
+        f"{n}. Knock, knock.\nWho’s there?\nOlive.\nOlive who?\n{answer}\n\n"
+        for n, answer in enumerate(["Olive you, I’m", "Olive the café crème"], 1)
+    )
+    req = Request(
+        url + "/jokes/import/text?dry_run=true",
+        headers = {"Authorization": "Bearer " + token, "Content-Type": "text/plain"},
+        data = text.encode(),
+        method = "POST",
+    )
+    with urlopen(req) as response:
+        report = json.loads(response.read())
+    assert len(report["jokes"]) == 2
+    print("ok")
+
+    print("importing with a bad joke: ", end="")
+    good = {"whos_there": "Ida", "answer_who": f"Ida {random_number} know!", "id": f"ida-{random_number}"}
+    bad = {"whos_there": " ", "answer_who": "Nobody", "id": f"nobody-{random_number}"}
+    results = curl("/jokes/import?policy=skip", method = "POST", data = [good, bad])
+    assert [r["status"] for r in results] == ["added", "failed"]
+    assert "whos_there" in results[1]["error"]
+    curl(f"/joke/{good['id']}", method = "DELETE")
+    results = curl("/jokes/import?policy=fail", method = "POST", data = [{**good, "id": good["id"] + "-2"}, bad])
+    assert [r["status"] for r in results] == ["rolled-back", "failed"]
     print("ok")
 
     joke["answer_who"] = f"Random Number {get_random_number()}"
@@ -125,6 +187,297 @@ try:
         data = joke,
     )
     print("ok")
+
+    print("updating with stale etag: ", end="")
+    e, _ = curl(
+        f"/joke/{joke_id}",
+        method = "PUT",
+        data = joke,
+        expect_error = True,
+        headers = {"If-Match": '"stale"'},
+    )
+    assert str(e) == "HTTP Error 412: Precondition Failed"
+    print("failed successfully")
+
+    print("rating new joke: ", end="")
+    rated = curl(f"/joke/{joke_id}/rating", method = "POST", data = {"rating": 4})
+    assert rated["rating"]["votes"] == 1
+    rated = curl(f"/joke/{joke_id}/rating", method = "POST", data = {"rating": 2})
+    assert rated["rating"] == {"votes": 1, "average": 2.0}
+    e, _ = curl(
+        f"/joke/{joke_id}/rating",
+        method = "POST",
+        data = {"rating": 6},
+        expect_error = True,
+    )
+    assert e.code == 422
+    top = curl("/jokes/top?min_votes=1")
+    assert joke_id in [j["id"] for j in top]
+    print("ok")
+
+    print("patching new joke: ", end="")
+    patched = curl(
+        f"/joke/{joke_id}",
+        method = "PATCH",
+        data = {"source": None},
+    )
+    assert "source" not in patched
+    patched = curl(
+        f"/joke/{joke_id}/tags",
+        method = "PATCH",
+        data = {"add": ["animal"]},
+    )
+    assert sorted(patched["tags"]) == ["animal", "deleteme"]
+    print("ok")
+
+    print("finding related jokes: ", end="")
+    related = curl(f"/joke/{joke_id}/related?limit=3")
+    assert len(related) <= 3 and joke_id not in [r["id"] for r in related]
+    assert all(r["score"] > 0 for r in related)
+    print("ok")
+
+    print("making a collection: ", end="")
+    collection = {"id": "test-set", "name": "Test set", "jokes": ["boo", joke_id]}
+    curl("/collections", method = "POST", data = collection)
+    try:
+        assert curl("/collections/test-set")["jokes"] == ["boo", joke_id]
+        assert curl("/collections/test-set/joke?position=1")["id"] == joke_id
+        assert curl("/collections/test-set/joke?position=2")["id"] == "boo"
+        collection["jokes"] = ["no-such-joke"]
+        e, problem = curl(
+            "/collections/test-set",
+            method = "PUT",
+            data = collection,
+            expect_error = True,
+        )
+        assert e.code == 422 and problem["errors"][0]["field"] == "jokes"
+    finally:
+        curl("/collections/test-set", method = "DELETE")
+    print("ok")
+
+    print("adding an invalid copy: ", end="")
+    e, r = curl("/joke/add", method = "POST", data = {"whos_there": joke["whos_there"], "answer_who": joke["answer_who"], "id": "Bad Id"}, expect_error = True)
+    assert e.code == 422 and r["errors"][0]["field"] == "id"
+    print("failed successfully")
+
+    print("listing duplicates: ", end="")
+    clusters = curl("/duplicates")
+    assert all(len(c["ids"]) > 1 for c in clusters)
+    print("ok")
+
+    print("checking joke history: ", end="")
+    history = curl(f"/joke/{joke_id}/history")
+    assert [r["action"] for r in history] == ["add", "update", "update", "update"]
+    assert "answer_who" in history[1]["diff"]
+    print("ok")
+
+    print("restoring original joke: ", end="")
+    restored = curl(
+        f"/joke/{joke_id}/history/{history[0]['revision']}/restore",
+        method = "POST",
+    )
+    assert restored["answer_who"] == history[0]["joke"]["answer_who"]
+    print("ok")
+
+    print("renaming a tag: ", end="")
+    old_tag, new_tag = f"tag-{get_random_number()}", f"tag-{get_random_number()}"
+    curl(f"/joke/{joke_id}/tags", method = "PATCH", data = {"add": [old_tag]})
+    try:
+        info = curl(f"/tags/{old_tag}/rename", method = "POST", data = {"to": new_tag})
+        assert info["count"] == 1 and info["aliases"] == [old_tag]
+        assert new_tag in curl(f"/joke/{joke_id}")["tags"]
+        patched = curl(
+            f"/joke/{joke_id}/tags",
+            method = "PATCH",
+            data = {"remove": [new_tag], "add": [old_tag]},
+        )
+        assert new_tag in patched["tags"] and old_tag not in patched["tags"]
+    finally:
+        curl(f"/tags/aliases/{old_tag}", method = "DELETE")
+    print("ok")
+
+    print("fetching joke as csv: ", end="")
+    req = Request(url + f"/joke/{joke_id}", headers = {"Accept": "text/csv"})
+    with urlopen(req) as response:
+        assert response.headers["Content-Type"].startswith("text/csv")
+        assert response.read().decode().splitlines()[1].startswith(joke_id + ",")
+        assert "User-Agent" in response.headers["Vary"]
+        csv_etag = response.headers["ETag"]
+    req = Request(url + f"/joke/{joke_id}", headers = {"Accept": "text/csv", "If-None-Match": csv_etag})
+    try:
+        urlopen(req)
+        assert False
+    except HTTPError as e:
+        assert e.code == 304 and "User-Agent" in e.headers["Vary"]
+    with urlopen(Request(url + f"/joke/{joke_id}")) as response:
+        assert response.headers["ETag"] != csv_etag
+        answer = json.loads(response.read())["answer_who"]
+    curl(f"/joke/{joke_id}", method = "PATCH", data = {"answer_who": answer}, headers = {"If-Match": csv_etag})
+    print("ok")
+
+    print("rating changes etag: ", end="")
+    with urlopen(Request(url + f"/joke/{joke_id}")) as response:
+        etag = response.headers["ETag"]
+    curl(f"/joke/{joke_id}/rating", method = "POST", data = {"rating": 5})
+    req = Request(url + f"/joke/{joke_id}", headers = {"If-None-Match": etag})
+    with urlopen(req) as response:
+        assert response.status == 200 and response.headers["ETag"] != etag
+    curl(f"/joke/{joke_id}", method = "PATCH", data = {"answer_who": answer}, headers = {"If-Match": etag})
+    print("ok")
+
+    print("scripting new joke: ", end="")
+    script = [
+        {"speaker": "teller", "line": "Knock knock!"},
+        {"speaker": "tellee", "line": "Who's there?"},
+        {"speaker": "teller", "line": "Interrupting number."},
+        {"speaker": "tellee", "line": "Interrupting number wh"},
+        {"speaker": "teller", "line": f"{random_number}!", "interrupts": True},
+    ]
+    scripted = curl(f"/joke/{joke_id}", method = "PATCH", data = {"script": script})
+    assert scripted["script"] == script
+    req = Request(url + f"/joke/{joke_id}", headers = {"Accept": "text/plain"})
+    with urlopen(req) as response:
+        lines = response.read().decode().splitlines()
+        assert lines[3:5] == ["Interrupting number wh—", f"{random_number}!"]
+    unscripted = curl(f"/joke/{joke_id}", method = "PATCH", data = {"script": None})
+    assert "script" not in unscripted
+    print("ok")
+
+    print("translating new joke: ", end="")
+    translation = {
+        "id": joke_id + "-es",
+        "whos_there": "Número al azar",
+        "answer_who": f"Número al azar {random_number}",
+        "lang": "es",
+        "translation_of": joke_id,
+    }
+    curl("/joke/add", method = "POST", data = translation)
+    try:
+        translations = curl(f"/joke/{joke_id}/translations")
+        assert [t["lang"] for t in translations] == ["en", "es"]
+        req = Request(url.removesuffix("/api/v1") + f"/?id={joke_id}&lang=es")
+        with urlopen(req) as response:
+            assert "¿Número al azar quién?" in response.read().decode()
+    finally:
+        curl(f"/joke/{translation['id']}", method = "DELETE")
+    print("ok")
+
+    print("localizing web pages: ", end="")
+    req = Request(url.removesuffix("/api/v1") + "/tell", headers = {"Accept-Language": "es, en;q=0.5"})
+    with urlopen(req) as response:
+        assert '<html lang="es">' in response.read().decode()
+    req = Request(url.removesuffix("/api/v1") + "/tell", headers = {"Cookie": "lang=en", "Accept-Language": "es"})
+    with urlopen(req) as response:
+        assert '<html lang="en">' in response.read().decode()
+    spanish = {"Accept-Language": "es"}
+    req = Request(url.removesuffix("/api/v1") + f"/?id={joke_id}", headers = spanish)
+    with urlopen(req) as response:
+        assert "quejidos=" in response.read().decode()
+    web = build_opener(HTTPCookieProcessor())
+    req = Request(url.removesuffix("/api/v1") + "/add?id=&who=&answer=Nobody", headers = spanish)
+    with web.open(req) as response:
+        assert "no puede estar vacío" in response.read().decode()
+    print("ok")
+
+    print("reviewing flagged joke: ", end="")
+    flagged = curl("/joke/add", method = "POST", data = {
+        "whos_there": "Beer",
+        "answer_who": f"Beer with me, it's number {random_number}!",
+    })
+    try:
+        assert flagged["flagged"] == ["beer"]
+        assert flagged["id"] in [j["id"] for j in curl("/review")]
+        e, _ = curl(f"/joke/{flagged['id']}?family_safe=true", expect_error = True)
+        assert e.code == 404
+        reviewed = curl(f"/joke/{flagged['id']}/review", method = "POST", data = {"audience": "adult"})
+        assert reviewed["audience"] == "adult" and "flagged" not in reviewed
+        safe = curl("/jokes?family_safe=true")
+        assert flagged["id"] not in [j["id"] for j in safe]
+        assert all(j["audience"] == "all-ages" for j in safe)
+    finally:
+        curl(f"/joke/{flagged['id']}", method = "DELETE")
+    print("ok")
+
+    print("restoring an exported jokebase: ", end="")
+    def export():
+        dump = curl("/export?format=json")
+        for record in dump:
+            record["tags"] = sorted(record.get("tags", []))
+        return dump
+    dump = export()
+    curl("/collections", method = "POST", data = {"id": "restore-set", "name": "Restore set", "jokes": [joke_id]})
+    try:
+        curl("/restore", method = "POST", data = dump)
+        assert export() == dump
+        assert curl("/collections/restore-set")["jokes"] == [joke_id]
+    finally:
+        curl("/collections/restore-set", method = "DELETE")
+    record = [r for r in dump if r["id"] == flagged["id"]][0]
+    assert record["flagged"] == [] and record["audience"] == "adult"
+    assert [r for r in dump if r["id"] == joke_id][0]["ratings"]
+    print("ok")
+
+    print("restoring an invalid jokebase: ", end="")
+    bad = [dict(r) for r in dump]
+    bad[1]["whos_there"] = ""
+    e, r = curl("/restore", method = "POST", data = bad, expect_error = True)
+    assert e.code == 422 and r["errors"][0]["field"] == "[1].whos_there"
+    assert export() == dump
+    print("failed successfully")
+
+    print("purging with bad day counts: ", end="")
+    for days in [-1, 9999999999999]:
+        e, r = curl(f"/trash/purge?older_than_days={days}", method = "POST", expect_error = True)
+        assert e.code == 422 and r["errors"][0]["field"] == "older_than_days"
+    print("failed successfully")
+
+    print("counting views: ", end="")
+    curl(f"/joke/{joke_id}?via=test-api")
+    time.sleep(1)
+    series = curl(f"/joke/{joke_id}/analytics?bucket=hour")
+    assert "test-api" in [p["channel"] for p in series]
+    counts = curl("/analytics?days=1&limit=1000")
+    views = [c for c in counts if c["id"] == joke_id][0]
+    assert views["total"] >= 2 and views["channels"]["test-api"] == 1
+    print("ok")
+
+    print("counting views with bad day counts: ", end="")
+    for path in ["/analytics", "/analytics/report", f"/joke/{joke_id}/analytics"]:
+        for days in [-1, 9999999999999]:
+            e, r = curl(f"{path}?days={days}", expect_error = True)
+            assert e.code == 422 and r["errors"][0]["field"] == "days"
+    print("failed successfully")
+
+    print("renaming new joke: ", end="")
+    tags = sorted(curl(f"/joke/{joke_id}").get("tags", []))
+    renamed = curl(f"/joke/{joke_id}/rename", method = "POST", data = {"id": joke_id + "-x"})
+    joke_id = renamed["id"]
+    assert sorted(renamed.get("tags", [])) == tags
+    assert curl(f"/joke/{joke_id}/history")[-1]["action"] == "rename"
+    old_id = joke_id.removesuffix("-x")
+    with urlopen(Request(url + f"/joke/{old_id}/translations")) as response:
+        assert response.url.endswith(f"/joke/{joke_id}/translations")
+    print("ok")
+
+    print("reusing an old id: ", end="")
+    old_id = joke_id.removesuffix("-x")
+    e, _ = curl("/joke/add", method = "POST", data = {**joke, "id": old_id}, expect_error = True)
+    assert e.code == 409
+    for policy, status in [("skip", "skipped"), ("overwrite", "failed")]:
+        results = curl(f"/jokes/import?policy={policy}", method = "POST", data = [{**joke, "id": old_id}])
+        assert results[0]["status"] == status
+    other = curl("/joke/add", method = "POST", data = {
+        "whos_there": "Lettuce",
+        "answer_who": f"Lettuce in, it's number {random_number}!",
+    })
+    try:
+        e, _ = curl(f"/joke/{other['id']}/rename", method = "POST", data = {"id": old_id}, expect_error = True)
+        assert e.code == 409
+    finally:
+        curl(f"/joke/{other['id']}", method = "DELETE")
+    joke_id = curl(f"/joke/{joke_id}/rename", method = "POST", data = {"id": old_id})["id"]
+    assert joke_id == old_id
+    print("failed successfully")
 finally:
     print("deleting new joke: ", end="")
     curl(
@@ -0,0 +1,85 @@
+#!/bin/sh
+# Check migration 0010 (schema hardening) against a scratch
+# database: built from the earlier migrations and seeded with
+# the kinds of bad data they allowed, or copied from an existing
+# database not yet migrated with `test-migration.sh knock_knock`.
+# The scratch database is dropped afterward.
+#
+# Uses PG_HOST, PG_USER and PG_PASSWORDFILE like the server.
+set -e
+
+MIGRATIONS=`dirname $0`/migrations
+SCRATCH=knock_knock_migration_test
+export PGHOST=${PG_HOST:-localhost} PGUSER=${PG_USER:-postgres}
+export PGOPTIONS="-c client_min_messages=warning"
+if [ -n "$PG_PASSWORDFILE" ]; then
+    export PGPASSWORD=`cat $PG_PASSWORDFILE`
+fi
+
+sql() {
+    psql -q -v ON_ERROR_STOP=1 -d $SCRATCH "$@"
+}
+
+check() {
+    RESULT=`sql -tA -c "$2"`
+    if [ "$RESULT" != "$3" ]; then
+        echo "FAILED: $1: got '$RESULT', expected '$3'"
+        exit 1
+    fi
+    echo "$1: ok"
+}
+
+dropdb --if-exists $SCRATCH 2>/dev/null
+trap 'dropdb --if-exists $SCRATCH' EXIT
+
+if [ -n "$1" ]; then
+    createdb $SCRATCH
+    pg_dump -d "$1" | sql > /dev/null
+    BEFORE=`sql -tA -c "SELECT count(DISTINCT (id, tag)) FROM tags WHERE id IS NOT NULL;"`
+else
+    createdb $SCRATCH
+    for MIGRATION in $MIGRATIONS/000[1-9]_*.up.sql; do
+        sql -f $MIGRATION
+    done
+    sql <<'EOF'
+INSERT INTO jokes (id, whos_there, answer_who) VALUES
+  ('boo', 'Boo', 'Don''t cry!'),
+  ('lettuce', 'Lettuce', 'Lettuce in!'),
+  ('old', 'Old', 'Old enough to know better.');
+INSERT INTO tags (id, tag) VALUES
+  ('boo', 'kids'), ('boo', 'kids'), ('boo', 'Kids '),
+  ('lettuce', 'food'), (NULL, 'orphan');
+INSERT INTO revisions (id, action, author, created, diff) VALUES
+  ('boo', 'add', 'test', '2024-01-01', '{}'),
+  ('boo', 'update', 'test', '2024-02-01', '{}');
+INSERT INTO aliases (alias, id) VALUES ('ghost', 'boo');
+INSERT INTO ratings (id, voter, rating) VALUES ('boo', 'user:test', 4);
+UPDATE tags SET tag = lower(btrim(tag));
+EOF
+    BEFORE=2
+fi
+
+sql -f $MIGRATIONS/0010_harden.up.sql
+
+check "tags deduplicated" "SELECT count(*) FROM tags;" "$BEFORE"
+check "jokes timestamped" "SELECT count(*) FROM jokes WHERE created IS NULL OR updated IS NULL;" 0
+check "tags unique" \
+    "SELECT count(*) FROM pg_constraint WHERE conname = 'tags_pkey';" 1
+if [ -z "$1" ]; then
+    check "times from revisions" \
+        "SELECT created::date || ' ' || updated::date FROM jokes WHERE id = 'boo';" \
+        "2024-01-01 2024-02-01"
+    sql -c "UPDATE jokes SET id = 'boo-hoo' WHERE id = 'boo';"
+    check "rename cascades" \
+        "SELECT (SELECT count(*) FROM tags WHERE id = 'boo-hoo')
+            + (SELECT count(*) FROM aliases WHERE id = 'boo-hoo')
+            + (SELECT count(*) FROM ratings WHERE id = 'boo-hoo');" 3
+    sql -c "DELETE FROM jokes WHERE id = 'boo-hoo';"
+    check "delete cascades" \
+        "SELECT (SELECT count(*) FROM tags) + (SELECT count(*) FROM aliases)
+            + (SELECT count(*) FROM ratings);" 1
+fi
+
+sql -f $MIGRATIONS/0010_harden.down.sql
+sql -f $MIGRATIONS/0010_harden.up.sql
+echo "down and up again: ok"